@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+/// 嵌套层数上限，避免恶意构造的数据导致栈溢出
+const MAX_DEPTH: usize = 64;
+
+/**
+ * bencode 值（.torrent 文件使用的编码）
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(dict) => dict.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * 解码完整的 bencode 数据，末尾有多余数据时返回错误
+ */
+pub fn decode(data: &[u8]) -> Result<Value, String> {
+    let mut position = 0;
+    let value = decode_value(data, &mut position, 0)?;
+    if position != data.len() {
+        return Err(format!("多余的数据，位置 {}", position));
+    }
+    Ok(value)
+}
+
+fn decode_value(data: &[u8], position: &mut usize, depth: usize) -> Result<Value, String> {
+    if depth > MAX_DEPTH {
+        return Err("嵌套层数过多".to_string());
+    }
+    match data.get(*position) {
+        Some(b'i') => {
+            *position += 1;
+            let digits = read_until(data, position, b'e')?;
+            let number = std::str::from_utf8(digits)
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(|| format!("无效的整数，位置 {}", *position))?;
+            Ok(Value::Int(number))
+        }
+        Some(b'l') => {
+            *position += 1;
+            let mut list = Vec::new();
+            while data.get(*position) != Some(&b'e') {
+                list.push(decode_value(data, position, depth + 1)?);
+            }
+            *position += 1;
+            Ok(Value::List(list))
+        }
+        Some(b'd') => {
+            *position += 1;
+            let mut dict = BTreeMap::new();
+            while data.get(*position) != Some(&b'e') {
+                let key = decode_bytes(data, position)?;
+                let value = decode_value(data, position, depth + 1)?;
+                dict.insert(key, value);
+            }
+            *position += 1;
+            Ok(Value::Dict(dict))
+        }
+        Some(b'0'..=b'9') => decode_bytes(data, position).map(Value::Bytes),
+        Some(c) => Err(format!(
+            "无效的类型标记 {:?}，位置 {}",
+            *c as char, *position
+        )),
+        None => Err("数据意外结束".to_string()),
+    }
+}
+
+/// 字节串：`<长度>:<内容>`
+fn decode_bytes(data: &[u8], position: &mut usize) -> Result<Vec<u8>, String> {
+    let digits = read_until(data, position, b':')?;
+    let length: usize = std::str::from_utf8(digits)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| format!("无效的字节串长度，位置 {}", *position))?;
+    let end = position
+        .checked_add(length)
+        .filter(|end| *end <= data.len())
+        .ok_or_else(|| "数据意外结束".to_string())?;
+    let bytes = data[*position..end].to_vec();
+    *position = end;
+    Ok(bytes)
+}
+
+/// 读取到分隔符为止（不含分隔符），并跳过分隔符
+fn read_until<'a>(data: &'a [u8], position: &mut usize, delimiter: u8) -> Result<&'a [u8], String> {
+    let start = *position;
+    let length = data[start..]
+        .iter()
+        .position(|&b| b == delimiter)
+        .ok_or_else(|| "数据意外结束".to_string())?;
+    *position = start + length + 1;
+    Ok(&data[start..start + length])
+}
+//EOP
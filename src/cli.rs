@@ -55,17 +55,49 @@ pub fn parse() -> Result<data::AppOptions, std::io::Error> {
         )
         .arg(
             arg!(
-                -t --"skip-tmp" ... "Skip the .tmp directory. [default]"
+            -o --organize ... "Match file organizing (moving) rule. [default]"
+        )
+                .action(ArgAction::SetTrue), // .hide(true),
+        )
+        .arg(
+            arg!(
+            -O --"no-organize" ... "Do not match file organizing rule."
+        )
+                .action(ArgAction::SetTrue)
+                .conflicts_with("organize"),
+        )
+        .arg(
+            arg!(
+                -t --"skip-tmp" ... "Skip entries in the `skip` list (.tmp by default). [default]"
             )
                 .action(ArgAction::SetTrue), // .hide(true),
         )
         .arg(
             arg!(
-            -T --"no-skip-tmp" ... "Do not skip the .tmp directory."
+            -T --"no-skip-tmp" ... "Do not apply the `skip` list."
         )
                 .action(ArgAction::SetTrue)
                 .conflicts_with("skip-tmp"),
         )
+        .arg(
+            arg!(--exclude <GLOB> "Never enter files or directories matching the glob (repeatable).")
+                .required(false)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            arg!(--"one-file-system" ... "Do not descend into directories on other filesystems.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"special-files" <POLICY> "How to treat FIFOs, sockets and device files.")
+                .required(false)
+                .value_parser(["skip", "include"])
+                .default_value("skip"),
+        )
+        .arg(
+            arg!(--"keep-shared-links" ... "Keep files that have hard links outside the target (e.g. a seeding directory).")
+                .action(ArgAction::SetTrue),
+        )
         .arg(
             arg!(
             -e --"remove-empty-dir" ... "Delete empty directories. [default]"
@@ -79,7 +111,79 @@ pub fn parse() -> Result<data::AppOptions, std::io::Error> {
                 .action(ArgAction::SetTrue)
                 .conflicts_with("remove-empty-dir"),
         )
+        .arg(
+            arg!(
+            -m --"merge-dirs" ... "Merge into existing directories when moving to parent. [default]"
+        )
+                .action(ArgAction::SetTrue), // .hide(true),
+        )
+        .arg(
+            arg!(
+            -M --"no-merge-dirs" ... "Do not merge directories, add a suffix instead."
+        )
+                .action(ArgAction::SetTrue)
+                .conflicts_with("merge-dirs"),
+        )
+        .arg(
+            arg!(
+            -f --flatten ... "Flatten redundant wrapper directories."
+        )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(
+            -F --"no-flatten" ... "Do not flatten wrapper directories, even if configured."
+        )
+                .action(ArgAction::SetTrue)
+                .conflicts_with("flatten"),
+        )
+        .arg(
+            arg!(--"verify-copy" ... "Verify MD5 after copying across filesystems.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--torrent ... "Delete BEP 47 padding files listed in .torrent files in or next to the target.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"torrent-foreign" ... "Also delete files not listed in the .torrent file (implies --torrent).")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--verify ... "Verify downloads against piece hashes in .torrent files before cleanup.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"verify-strict" ... "Skip cleanup of torrent contents that fail verification (implies --verify).")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"verify-manifest" ... "Verify .sfv/.md5/.sha256 checksum manifests and delete those that pass.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"preserve-dir-times" ... "Restore directory access and modification times after cleanup.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"rename-root" ... "Allow renaming the target directory itself.")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            arg!(--"i-know-what-im-doing" ... "Allow a system directory, home directory or mount point as the target.")
+                .action(ArgAction::SetTrue),
+        )
         .arg(arg!(--prune ... "Perform the prune action.").action(ArgAction::SetTrue))
+        .arg(
+            arg!(--"on-locked" <MODE> "What to do when another run holds the lock on the tree.")
+                .required(false)
+                .value_parser(["wait", "skip", "fail"])
+                .default_value("wait"),
+        )
+        .arg(
+            arg!(--force ... "Perform the prune action even if the plan exceeds the safety limits.")
+                .action(ArgAction::SetTrue),
+        )
         .arg(arg!(
         -v --verbose ... "Verbose mode."
     ));
@@ -97,8 +201,41 @@ pub fn parse() -> Result<data::AppOptions, std::io::Error> {
         enable_prune_empty_dir: matches.get_flag("remove-empty-dir")
             || !matches.get_flag("no-remove-empty-dir"),
         enable_renaming: matches.get_flag("rename") || !matches.get_flag("no-rename"),
-        skip_parent_tmp: matches.get_flag("skip-tmp") || !matches.get_flag("no-skip-tmp"),
+        enable_organizing: matches.get_flag("organize") || !matches.get_flag("no-organize"),
+        merge_dirs: matches.get_flag("merge-dirs") || !matches.get_flag("no-merge-dirs"),
+        flatten: if matches.get_flag("flatten") {
+            Some(true)
+        } else if matches.get_flag("no-flatten") {
+            Some(false)
+        } else {
+            None
+        },
+        verify_copy: matches.get_flag("verify-copy"),
+        preserve_dir_times: matches.get_flag("preserve-dir-times"),
+        torrent: matches.get_flag("torrent") || matches.get_flag("torrent-foreign"),
+        torrent_foreign: matches.get_flag("torrent-foreign"),
+        verify: matches.get_flag("verify") || matches.get_flag("verify-strict"),
+        verify_strict: matches.get_flag("verify-strict"),
+        verify_manifest: matches.get_flag("verify-manifest"),
+        enable_skip: matches.get_flag("skip-tmp") || !matches.get_flag("no-skip-tmp"),
+        exclude: matches
+            .get_many::<String>("exclude")
+            .map(|globs| globs.cloned().collect())
+            .unwrap_or_default(),
+        rename_root: matches.get_flag("rename-root"),
+        allow_dangerous_root: matches.get_flag("i-know-what-im-doing"),
         prune: matches.get_flag("prune"),
+        on_locked: match matches.get_one::<String>("on-locked").map(String::as_str) {
+            Some("skip") => data::LockMode::Skip,
+            Some("fail") => data::LockMode::Fail,
+            _ => data::LockMode::Wait,
+        },
+        one_file_system: matches.get_flag("one-file-system"),
+        include_special_files: matches
+            .get_one::<String>("special-files")
+            .is_some_and(|policy| policy == "include"),
+        keep_shared_links: matches.get_flag("keep-shared-links"),
+        force: matches.get_flag("force"),
         verbose: matches.get_count("verbose"),
         config_file: match matches.get_one::<PathBuf>("config") {
             None => util::guess_path(".cleanup-patterns.yml", util::get_guess_paths(&target_path))
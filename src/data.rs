@@ -6,6 +6,15 @@ pub enum Operation {
     Delete,
     Rename,
     MoveToParent, // 当目录名被完全清理时，将内容移动到父目录
+    Move,         // 按整理规则移动到目标目录
+}
+
+/// 目录树已被其他清理进程锁定时的处理方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LockMode {
+    Wait,
+    Skip,
+    Fail,
 }
 
 #[derive(Debug)]
@@ -13,9 +22,27 @@ pub struct AppOptions {
     pub enable_deletion: bool,
     pub enable_hash_matching: bool,
     pub enable_renaming: bool,
+    pub enable_organizing: bool,
     pub enable_prune_empty_dir: bool,
-    pub skip_parent_tmp: bool,
+    pub merge_dirs: bool,
+    pub flatten: Option<bool>,
+    pub verify_copy: bool,
+    pub preserve_dir_times: bool,
+    pub torrent: bool,
+    pub torrent_foreign: bool,
+    pub verify: bool,
+    pub verify_strict: bool,
+    pub verify_manifest: bool,
+    pub enable_skip: bool,
+    pub exclude: Vec<String>,
+    pub rename_root: bool,
+    pub allow_dangerous_root: bool,
     pub prune: bool,
+    pub on_locked: LockMode,
+    pub force: bool,
+    pub one_file_system: bool,
+    pub include_special_files: bool,
+    pub keep_shared_links: bool,
     pub verbose: u8,
     pub config_file: PathBuf,
     pub target_path: PathBuf,
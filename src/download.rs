@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::pconfig::InProgressConfig;
+use crate::util;
+
+/// 默认的下载中标记扩展名：aria2 控制文件、浏览器和 qBittorrent 的未完成文件
+pub const DEFAULT_MARKER_EXTENSIONS: [&str; 4] = ["aria2", "part", "!qB", "crdownload"];
+
+/**
+ * 找出仍在下载中的条目：带有标记扩展名的文件（`<name>.aria2` 表示同目录下的 `<name>` 正在下载），
+ * 或者在最近的时间窗口内修改过的文件。
+ * 返回要整体跳过的路径及原因：下载条目所在的目录；条目直接位于目标路径中时，只跳过条目自身和标记文件
+ */
+pub fn find_in_progress(
+    paths: &[PathBuf],
+    target_path: &Path,
+    config: &InProgressConfig,
+) -> Vec<(PathBuf, String)> {
+    let now = SystemTime::now();
+    let window = Duration::from_secs(config.recent_seconds);
+    let mut found: Vec<(PathBuf, String)> = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let (stem, ext) = util::split_extension(name);
+        let is_marker = !stem.is_empty()
+            && !ext.is_empty()
+            && config
+                .markers
+                .iter()
+                .any(|marker| marker.eq_ignore_ascii_case(&ext[1..]));
+        let (item, reason) = if is_marker {
+            (path.with_file_name(stem), format!("存在下载标记 {}", name))
+        } else if config.recent_seconds > 0
+            && path
+                .symlink_metadata()
+                .and_then(|m| m.modified())
+                .is_ok_and(|mtime| now.duration_since(mtime).unwrap_or_default() < window)
+            && path.is_file()
+        {
+            (
+                path.clone(),
+                format!("{} 秒内有修改", config.recent_seconds),
+            )
+        } else {
+            continue;
+        };
+
+        match item.parent() {
+            Some(parent) if parent != target_path && parent.starts_with(target_path) => {
+                found.push((parent.to_path_buf(), reason));
+            }
+            _ => {
+                if &item != path && util::path_exists(&item) {
+                    found.push((item, reason.clone()));
+                }
+                found.push((path.clone(), reason));
+            }
+        }
+    }
+
+    // 去掉已被其他跳过的目录包含的路径
+    found.sort();
+    let mut skipped: Vec<(PathBuf, String)> = Vec::new();
+    for (path, reason) in found {
+        if !skipped.iter().any(|(root, _)| path.starts_with(root)) {
+            skipped.push((path, reason));
+        }
+    }
+    skipped
+}
+//EOP
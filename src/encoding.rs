@@ -0,0 +1,212 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+
+use colored::*;
+use encoding_rs::Encoding;
+
+/// `transcode: true` 时尝试的文件名编码（按顺序）
+pub const DEFAULT_TRANSCODE_ENCODINGS: [&str; 3] = ["gb18030", "shift_jis", "big5"];
+/// 修复乱码时尝试的原始编码（按顺序）
+pub const DEFAULT_REPAIR_ENCODINGS: [&str; 3] = ["gb18030", "big5", "shift_jis"];
+
+/// 误用的编码名称和将字符还原为字节的函数
+type Misdecoding = (&'static str, fn(char) -> Option<u8>);
+const MISDECODINGS: [Misdecoding; 2] = [("latin-1", encode_latin1), ("cp437", encode_cp437)];
+
+/// CP437 中 0x80~0xFF 对应的字符
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»\
+    ░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+/**
+ * 将文件名转为可匹配的字符串：有效的 UTF-8 原样保留，无效字节转义为 `\xNN`
+ *
+ * 对于有效的 UTF-8 文件名结果与原名相同，因此 `remove` 规则对两种文件名都适用。
+ */
+pub fn escape_os_str(name: &OsStr) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for chunk in name.as_bytes().utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{:02X}", byte));
+        }
+    }
+    escaped
+}
+
+/**
+ * 用候选编码解码无效 UTF-8 的文件名。多数 Big5 和 Shift-JIS 字节串同时也是有效的 GB18030，
+ * 因此不取第一个能解码的结果，而是选择常用字比例最高的一个（比例相同时按候选编码的顺序），
+ * 返回解码后的文件名和所用编码的名称；没有足够可信的结果时返回 `None`。
+ */
+pub fn transcode(name: &OsStr, encodings: &[&'static Encoding]) -> Option<(String, &'static str)> {
+    let mut best: Option<(f64, String, &'static str)> = None;
+    for encoding in encodings {
+        let Some(decoded) =
+            encoding.decode_without_bom_handling_and_without_replacement(name.as_bytes())
+        else {
+            continue;
+        };
+        if !is_plausible(&decoded) {
+            continue;
+        }
+        let score = common_ratio(&decoded, encoding);
+        if best
+            .as_ref()
+            .is_none_or(|(best_score, ..)| score > *best_score)
+        {
+            best = Some((score, decoded.into_owned(), encoding.name()));
+        }
+    }
+    // 与修复乱码相同，常用字不到一半时不采用
+    best.filter(|(score, ..)| *score >= 0.5)
+        .map(|(_, decoded, encoding)| (decoded, encoding))
+}
+
+/**
+ * 修复乱码文件名：原本是 GBK/Big5/Shift-JIS 等编码的字节被误当作 Latin-1 或 CP437 解码。
+ * 将文件名按误用的编码还原为字节，再用候选编码解码；多个结果都有效时，
+ * 选择常用字比例最高的一个（比例相同时按候选编码的顺序），返回修复后的文件名、误用的编码和原始编码的名称。
+ */
+pub fn repair_mojibake(
+    name: &str,
+    encodings: &[&'static Encoding],
+) -> Option<(String, &'static str, &'static str)> {
+    // 只有 ASCII 或已经包含中日韩文字的文件名不是这类乱码
+    if name.is_ascii() || name.chars().any(is_cjk) {
+        return None;
+    }
+    let mut best: Option<(f64, String, &'static str, &'static str)> = None;
+    for (misdecoding, encode) in MISDECODINGS {
+        let Some(bytes) = name.chars().map(encode).collect::<Option<Vec<u8>>>() else {
+            continue;
+        };
+        for encoding in encodings {
+            let Some(repaired) =
+                encoding.decode_without_bom_handling_and_without_replacement(&bytes)
+            else {
+                continue;
+            };
+            if !is_plausible(&repaired) {
+                continue;
+            }
+            let score = common_ratio(&repaired, encoding);
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, ..)| score > *best_score)
+            {
+                best = Some((score, repaired.into_owned(), misdecoding, encoding.name()));
+            }
+        }
+    }
+    // 常用字不到一半时，多半是把正常的文件名误判为乱码
+    best.filter(|(score, ..)| *score >= 0.5)
+        .map(|(_, repaired, misdecoding, encoding)| (repaired, misdecoding, encoding))
+}
+
+/**
+ * 计算非 ASCII 字符中属于该编码常用字区（常用汉字、假名和全角标点）的比例
+ */
+fn common_ratio(text: &str, encoding: &'static Encoding) -> f64 {
+    let is_common: fn(u8, u8) -> bool =
+        if encoding == encoding_rs::GB18030 || encoding == encoding_rs::GBK {
+            // GB2312 符号区和一级汉字（尾字节低于 0xA1 的是 GBK 扩展区）
+            |lead, trail| matches!(lead, 0xA1..=0xA3 | 0xB0..=0xD7) && trail >= 0xA1
+        } else if encoding == encoding_rs::BIG5 {
+            // 符号区和常用汉字
+            |lead, _| matches!(lead, 0xA1..=0xC6)
+        } else if encoding == encoding_rs::SHIFT_JIS {
+            // 符号区、假名和第一水准汉字
+            |lead, _| matches!(lead, 0x81..=0x84 | 0x88..=0x98)
+        } else {
+            return 1.0;
+        };
+    let (mut total, mut common) = (0, 0);
+    for c in text.chars().filter(|c| !c.is_ascii()) {
+        total += 1;
+        let mut buffer = [0u8; 4];
+        let (bytes, _, unmappable) = encoding.encode(c.encode_utf8(&mut buffer));
+        if let [lead, trail] = bytes.as_ref() {
+            if !unmappable && is_common(*lead, *trail) {
+                common += 1;
+            }
+        }
+    }
+    if total == 0 {
+        0.0
+    } else {
+        common as f64 / total as f64
+    }
+}
+
+/// Latin-1 字符还原为字节；0x80~0x9F 同时接受 Windows-1252 中的字符
+fn encode_latin1(c: char) -> Option<u8> {
+    if (c as u32) < 0x100 {
+        return Some(c as u8);
+    }
+    let mut buffer = [0u8; 4];
+    let (bytes, _, unmappable) = encoding_rs::WINDOWS_1252.encode(c.encode_utf8(&mut buffer));
+    match bytes.as_ref() {
+        [byte] if !unmappable => Some(*byte),
+        _ => None,
+    }
+}
+
+/// CP437 字符还原为字节
+fn encode_cp437(c: char) -> Option<u8> {
+    if c.is_ascii() {
+        return Some(c as u8);
+    }
+    CP437_HIGH
+        .chars()
+        .position(|high| high == c)
+        .map(|index| 0x80 + index as u8)
+}
+
+/// 修复结果是否合理：包含中日韩文字，没有控制字符，非 ASCII 字符都是中日韩文字或标点
+fn is_plausible(name: &str) -> bool {
+    name.chars().any(is_cjk)
+        && name
+            .chars()
+            .all(|c| !c.is_control() && (c.is_ascii() || is_cjk(c) || is_cjk_punctuation(c)))
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'   // 平假名、片假名
+        | '\u{3400}'..='\u{4DBF}' // 扩展 A
+        | '\u{4E00}'..='\u{9FFF}' // 基本汉字
+        | '\u{AC00}'..='\u{D7AF}' // 韩文音节
+        | '\u{F900}'..='\u{FAFF}' // 兼容汉字
+    )
+}
+
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c,
+        '\u{00B7}' | '\u{00D7}'
+        | '\u{2010}'..='\u{206F}' // 通用标点
+        | '\u{2460}'..='\u{24FF}' // 带圈数字
+        | '\u{3000}'..='\u{303F}' // 中日韩标点
+        | '\u{FF00}'..='\u{FFEF}' // 全角字符
+    )
+}
+
+/**
+ * 将编码名称（如 gbk、shift_jis、big5）解析为编码，无法识别的名称被忽略并输出警告
+ */
+pub fn parse_encodings(labels: &[String]) -> Vec<&'static Encoding> {
+    labels
+        .iter()
+        .filter_map(|label| {
+            let encoding = Encoding::for_label(label.trim().as_bytes());
+            if encoding.is_none() {
+                eprintln!(
+                    "{} 无法识别的编码名称，已忽略: {}",
+                    "[警告]".yellow(),
+                    label
+                );
+            }
+            encoding
+        })
+        .collect()
+}
+//EOP
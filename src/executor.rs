@@ -0,0 +1,971 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{create_dir_all, read_dir, remove_dir, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use colored::*;
+
+use crate::data;
+use crate::fsops;
+use crate::util;
+
+/// 重命名计划中的一个步骤
+#[derive(Debug)]
+enum RenameStep {
+    /// 先将源文件移动到临时名称：为打破循环（`cycle`），或目标与源文件仅大小写不同
+    ToTemp {
+        source: PathBuf,
+        target: PathBuf,
+        cycle: bool,
+    },
+    /// 移动到最终名称
+    Final {
+        original: PathBuf,
+        source: PathBuf,
+        target: PathBuf,
+    },
+}
+
+/// 按父目录分组的重命名操作，键为（深度倒序，父目录）
+type RenameGroups = BTreeMap<(Reverse<usize>, PathBuf), Vec<(PathBuf, String)>>;
+
+/// 等待执行的重命名：当前路径（可能是临时名称）、原始路径、目标路径
+/// `case_only` 表示大小写不敏感的文件系统上目标与源文件仅大小写不同，需要经由临时名称
+#[derive(Debug)]
+struct PendingRename {
+    current: PathBuf,
+    original: PathBuf,
+    target: PathBuf,
+    case_only: bool,
+}
+
+/**
+ * 移动记录：按执行顺序排列的 (源路径, 目标路径)，目标为空表示预览中已删除的目录。
+ * 同时维护预览用的索引，在真实目录内容上叠加移动记录时不必逐条扫描：
+ * 真实路径 => 当前位置，当前位置 => 真实路径，目录 => 直接移入其中的条目，
+ * 以及目录 => 移入其中（任意深度）的条目（目录再次移动时，其中的条目随之更新）
+ */
+#[derive(Debug, Default)]
+pub struct MoveLog {
+    entries: Vec<(PathBuf, PathBuf)>,
+    forward: HashMap<PathBuf, PathBuf>,
+    reverse: HashMap<PathBuf, PathBuf>,
+    children: HashMap<PathBuf, HashSet<PathBuf>>,
+    descendants: HashMap<PathBuf, HashSet<PathBuf>>,
+}
+
+impl std::ops::Deref for MoveLog {
+    type Target = [(PathBuf, PathBuf)];
+
+    fn deref(&self) -> &Self::Target {
+        &self.entries
+    }
+}
+
+impl MoveLog {
+    /**
+     * 追加一条移动记录：`from` 为当前位置，之前移入其中的条目随之更新到新位置
+     */
+    pub fn push(&mut self, (from, to): (PathBuf, PathBuf)) {
+        let real = self.real_path(&from);
+        let moved_in: Vec<PathBuf> = self
+            .descendants
+            .get(&from)
+            .map(|paths| paths.iter().cloned().collect())
+            .unwrap_or_default();
+        for path in moved_in {
+            if let Some(path_real) = self.unindex(&path) {
+                let rest = path.strip_prefix(&from).unwrap();
+                self.index(&to, rest, path_real);
+            }
+        }
+        self.unindex(&from);
+        self.index(&to, Path::new(""), real);
+        self.entries.push((from, to));
+    }
+
+    /// 记录真实路径 `real` 移动到了 `dir/rest`（`dir` 为空表示已删除）
+    fn index(&mut self, dir: &Path, rest: &Path, real: PathBuf) {
+        if dir.as_os_str().is_empty() {
+            self.forward.insert(real, PathBuf::new());
+            return;
+        }
+        let path = if rest.as_os_str().is_empty() {
+            dir.to_path_buf()
+        } else {
+            dir.join(rest)
+        };
+        if let Some(parent) = path.parent() {
+            self.children
+                .entry(parent.to_path_buf())
+                .or_default()
+                .insert(path.clone());
+        }
+        for ancestor in path.ancestors().skip(1) {
+            self.descendants
+                .entry(ancestor.to_path_buf())
+                .or_default()
+                .insert(path.clone());
+        }
+        self.reverse.insert(path.clone(), real.clone());
+        self.forward.insert(real, path);
+    }
+
+    /// 移除当前位置上移入的条目，返回其真实路径
+    fn unindex(&mut self, path: &Path) -> Option<PathBuf> {
+        let real = self.reverse.remove(path)?;
+        if let Some(siblings) = path.parent().and_then(|p| self.children.get_mut(p)) {
+            siblings.remove(path);
+        }
+        for ancestor in path.ancestors().skip(1) {
+            if let Some(paths) = self.descendants.get_mut(ancestor) {
+                paths.remove(path);
+            }
+        }
+        Some(real)
+    }
+
+    /// 真实路径的当前位置（所在的目录被移动时随之移动）；已删除时为空路径
+    fn remap(&self, path: &Path) -> PathBuf {
+        for ancestor in path.ancestors() {
+            if let Some(to) = self.forward.get(ancestor) {
+                if to.as_os_str().is_empty() {
+                    return PathBuf::new();
+                }
+                let rest = path.strip_prefix(ancestor).unwrap();
+                return if rest.as_os_str().is_empty() {
+                    to.clone()
+                } else {
+                    to.join(rest)
+                };
+            }
+        }
+        path.to_path_buf()
+    }
+
+    /// 当前位置对应的真实路径
+    fn real_path(&self, path: &Path) -> PathBuf {
+        for ancestor in path.ancestors() {
+            if let Some(real) = self.reverse.get(ancestor) {
+                let rest = path.strip_prefix(ancestor).unwrap();
+                return if rest.as_os_str().is_empty() {
+                    real.clone()
+                } else {
+                    real.join(rest)
+                };
+            }
+        }
+        path.to_path_buf()
+    }
+}
+
+/**
+ * 执行重命名操作
+ *
+ * 同一目录下的重命名作为一个整体规划：将被其他重命名腾出的名称不视为冲突，
+ * 链式重命名（a -> b, b -> c）按依赖顺序执行，循环重命名（a -> b, b -> a）经由临时名称完成。
+ * `moves` 为之前执行的移动记录，预览模式下用于判断目标是否已存在。
+ */
+pub fn execute_renames(
+    rename_operations: Vec<(PathBuf, String)>,
+    options: &data::AppOptions,
+    moves: &mut MoveLog,
+    identities: &fsops::Identities,
+) {
+    // 按父目录分组：深度大的（子项）先处理，深度小的（父项）后处理
+    let mut groups: RenameGroups = BTreeMap::new();
+    for (original_path, new_file_name) in rename_operations {
+        let parent = match original_path.parent() {
+            Some(parent) => parent.to_path_buf(),
+            None => {
+                eprintln!("{} 无法获取父目录: {:?}", "[错误]".red(), original_path);
+                continue;
+            }
+        };
+        groups
+            .entry((Reverse(parent.components().count()), parent))
+            .or_default()
+            .push((original_path, new_file_name));
+    }
+
+    for ((_, parent), operations) in groups {
+        for step in plan_renames(&parent, operations, options, moves) {
+            run_step(step, options, moves, identities);
+        }
+    }
+}
+
+/**
+ * 规划同一目录下的重命名，返回按执行顺序排列的步骤
+ */
+fn plan_renames(
+    parent: &Path,
+    mut operations: Vec<(PathBuf, String)>,
+    options: &data::AppOptions,
+    moves: &MoveLog,
+) -> Vec<RenameStep> {
+    operations.sort();
+
+    // 本组内所有源文件名，它们在执行过程中会被腾出
+    let source_names: HashSet<String> = operations
+        .iter()
+        .filter_map(|(path, _)| path.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .collect();
+    // 已被本组其他重命名占用的目标名称
+    let mut claimed_names: HashSet<String> = HashSet::new();
+    let mut pending: Vec<PendingRename> = Vec::with_capacity(operations.len());
+
+    for (original_path, new_file_name) in operations {
+        println!(
+            "{} {:#?} ==> {}",
+            "[*]".yellow(),
+            original_path,
+            new_file_name
+        );
+
+        // 处理重命名冲突：如果目标路径已存在，添加后缀 (1), (2), ...
+        // 大小写不敏感的文件系统上，仅大小写不同的名称“已存在”的是源文件自身，不算冲突
+        let is_free = |name: &str| {
+            !claimed_names.contains(name)
+                && (source_names.contains(name)
+                    || !preview_exists(&parent.join(name), options, moves)
+                    || is_case_variant(&original_path, &parent.join(name), options, moves))
+        };
+        let final_name = match util::find_available_name(&new_file_name, is_free) {
+            Some(name) => name,
+            None => {
+                eprintln!(
+                    "{} 无法找到可用的重命名目标（尝试了999个后缀）: {:?}",
+                    "[错误]".red(),
+                    original_path
+                );
+                continue;
+            }
+        };
+        if final_name != new_file_name {
+            println!(
+                "  {} 目标已存在，使用新名称: {}",
+                "[提示]".blue(),
+                final_name
+            );
+        }
+
+        let target = parent.join(&final_name);
+        claimed_names.insert(final_name);
+        if target == original_path {
+            // 冲突后缀恰好回到原名，无需操作
+            continue;
+        }
+        pending.push(PendingRename {
+            case_only: is_case_variant(&original_path, &target, options, moves),
+            current: original_path.clone(),
+            original: original_path,
+            target,
+        });
+    }
+
+    // 按依赖排序：目标仍被其他待处理项占用时需要等待；全部互相等待时说明存在循环
+    let mut steps = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let ready = pending.iter().position(|item| {
+            !pending
+                .iter()
+                .any(|other| !std::ptr::eq(other, item) && other.current == item.target)
+        });
+        match ready {
+            Some(index) => {
+                let mut item = pending.remove(index);
+                if item.case_only && item.current == item.original {
+                    let temp_path = temp_path_in(parent, |path| {
+                        preview_exists(path, options, moves)
+                            || path.file_name().is_some_and(|n| {
+                                claimed_names.contains(n.to_string_lossy().as_ref())
+                            })
+                    });
+                    claimed_names.insert(
+                        temp_path
+                            .file_name()
+                            .unwrap()
+                            .to_string_lossy()
+                            .into_owned(),
+                    );
+                    steps.push(RenameStep::ToTemp {
+                        source: item.current,
+                        target: temp_path.clone(),
+                        cycle: false,
+                    });
+                    item.current = temp_path;
+                }
+                steps.push(RenameStep::Final {
+                    original: item.original,
+                    source: item.current,
+                    target: item.target,
+                });
+            }
+            None => {
+                let temp_path = temp_path_in(parent, |path| {
+                    preview_exists(path, options, moves)
+                        || path
+                            .file_name()
+                            .is_some_and(|n| claimed_names.contains(n.to_string_lossy().as_ref()))
+                });
+                claimed_names.insert(
+                    temp_path
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned(),
+                );
+                let item = &mut pending[0];
+                steps.push(RenameStep::ToTemp {
+                    source: item.current.clone(),
+                    target: temp_path.clone(),
+                    cycle: true,
+                });
+                item.current = temp_path;
+            }
+        }
+    }
+    steps
+}
+
+/**
+ * 执行重命名步骤，将完成（或预览中）的重命名追加到 `moves`，用于之后按新路径恢复目录时间、处理附属文件
+ */
+fn run_step(
+    step: RenameStep,
+    options: &data::AppOptions,
+    moves: &mut MoveLog,
+    identities: &fsops::Identities,
+) {
+    match step {
+        RenameStep::ToTemp {
+            source,
+            target,
+            cycle,
+        } => {
+            if cycle {
+                println!(
+                    "  {} 检测到循环重命名，暂存 {:?} 为临时名称: {}",
+                    "[提示]".blue(),
+                    source,
+                    target.display()
+                );
+            } else {
+                println!(
+                    "  {} 目标与源文件仅大小写不同（大小写不敏感的文件系统），经由临时名称: {}",
+                    "[提示]".blue(),
+                    target.display()
+                );
+            }
+            if options.prune {
+                if !unchanged(&moves.real_path(&source), &source, identities) {
+                    return;
+                }
+                if let Err(e) = fsops::rename_noreplace(&source, &target) {
+                    eprintln!(
+                        "{} 重命名文件失败 {:?} -> {:?}: {}",
+                        "[错误]".red(),
+                        source,
+                        target,
+                        e
+                    );
+                }
+            }
+        }
+        RenameStep::Final {
+            original,
+            source,
+            target,
+        } => {
+            if options.prune {
+                // 经由临时名称时，源文件在暂存前位于 `original`
+                if !unchanged(&moves.real_path(&original), &source, identities) {
+                    return;
+                }
+                match rename_resolving_conflicts(&source, target.clone(), options) {
+                    Ok(final_path) => {
+                        println!("--> {}", final_path.display().to_string().cyan());
+                        moves.push((original, final_path));
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                        eprintln!(
+                            "{} 源文件不存在，可能已被父目录操作影响: {:?}",
+                            "[警告]".yellow(),
+                            original
+                        );
+                    }
+                    Err(e) => {
+                        eprintln!(
+                            "{} 重命名文件失败 {:?} -> {:?}: {}",
+                            "[错误]".red(),
+                            original,
+                            target,
+                            e
+                        );
+                    }
+                }
+            } else {
+                if source != original {
+                    println!("  --> 预览：{} 经由临时名称完成重命名", target.display());
+                }
+                moves.push((original, target));
+            }
+        }
+    }
+}
+
+/**
+ * 执行“移动内容到父目录”操作（目录名被完全清理，或展开冗余的包装目录时）
+ *
+ * 子项与父目录中已有条目重名时：目录按合并策略递归合并，文件使用冲突后缀 (1), (2), ...
+ * 子项与目录自身同名时（如 Show.S01/Show.S01/），先将目录移到临时名称以腾出位置。
+ * 所有（预计）移动的 (源路径, 目标路径) 追加到 `moves`；预览模式下不修改文件系统，
+ * 而是在真实目录内容上叠加之前的移动记录，使预览结果与实际执行一致。
+ */
+pub fn execute_move_to_parent(
+    dir_path: &Path,
+    options: &data::AppOptions,
+    moves: &mut MoveLog,
+    identities: &fsops::Identities,
+) {
+    println!("{} {:#?} ==> 移动内容到父目录", "[*]".yellow(), dir_path);
+    if options.prune && !unchanged(&moves.real_path(dir_path), dir_path, identities) {
+        return;
+    }
+
+    let (parent_dir, dir_name) = match (dir_path.parent(), dir_path.file_name()) {
+        (Some(parent_dir), Some(dir_name)) => (parent_dir, dir_name),
+        _ => {
+            eprintln!("{} 无法获取父目录: {:?}", "[错误]".red(), dir_path);
+            return;
+        }
+    };
+    if !options.prune {
+        println!(
+            "  --> 预览：将移动目录内容到 {}",
+            parent_dir.display().to_string().cyan()
+        );
+    }
+
+    // 子项与目录自身同名：先把目录移到临时名称
+    let mut source_dir = dir_path.to_path_buf();
+    if preview_exists(&dir_path.join(dir_name), options, moves) {
+        let temp_path = temp_path_in(parent_dir, |path| preview_exists(path, options, moves));
+        println!(
+            "  {} 子项与目录同名，先暂存为临时名称: {}",
+            "[提示]".blue(),
+            temp_path.display()
+        );
+        if options.prune {
+            if let Err(e) = fsops::rename_noreplace(dir_path, &temp_path) {
+                eprintln!(
+                    "{} 重命名文件失败 {:?} -> {:?}: {}",
+                    "[错误]".red(),
+                    dir_path,
+                    temp_path,
+                    e
+                );
+                return;
+            }
+        }
+        moves.push((dir_path.to_path_buf(), temp_path.clone()));
+        source_dir = temp_path;
+    }
+
+    if merge_dir_into(&source_dir, parent_dir, options, moves, identities) {
+        if !options.prune {
+            // 预览：记录目录已被删除
+            moves.push((source_dir, PathBuf::new()));
+            return;
+        }
+        // 移动完成后删除空目录
+        match remove_dir(&source_dir) {
+            Ok(_) => println!(
+                "  --> 删除空目录 {}",
+                source_dir.display().to_string().cyan()
+            ),
+            Err(e) => {
+                eprintln!("{} 删除空目录失败 {:?}: {}", "[错误]".red(), source_dir, e);
+            }
+        }
+    }
+}
+
+/**
+ * 执行整理（移动）操作：将条目移动到目标路径，自动创建目标目录
+ *
+ * 目标已存在时：目录按合并策略递归合并，文件使用冲突后缀 (1), (2), ...
+ * 移动后清理源位置留下的空目录（不超出目标路径）。
+ */
+pub fn execute_move(
+    source: &Path,
+    dest: &Path,
+    options: &data::AppOptions,
+    moves: &mut MoveLog,
+    identities: &fsops::Identities,
+) {
+    println!("{} {:#?} ==> {}", "[>]".cyan(), source, dest.display());
+    if options.prune && !unchanged(&moves.real_path(source), source, identities) {
+        return;
+    }
+
+    if dest.starts_with(source) {
+        eprintln!("{} 不能移动到自身内部: {:?}", "[错误]".red(), source);
+        return;
+    }
+    let (dest_dir, dest_name) = match (dest.parent(), dest.file_name()) {
+        (Some(dest_dir), Some(dest_name)) => (dest_dir, dest_name.to_string_lossy()),
+        _ => {
+            eprintln!("{} 无效的目标路径: {:?}", "[错误]".red(), dest);
+            return;
+        }
+    };
+    if options.prune {
+        if let Err(e) = create_dir_all(dest_dir) {
+            eprintln!("{} 创建目录失败 {:?}: {}", "[错误]".red(), dest_dir, e);
+            return;
+        }
+    }
+
+    let mut target_path = dest.to_path_buf();
+    if preview_exists(&target_path, options, moves) {
+        // 目录冲突：递归合并到已存在的目录
+        if options.merge_dirs
+            && preview_is_real_dir(source, options, moves)
+            && preview_is_real_dir(&target_path, options, moves)
+        {
+            println!(
+                "  {} 目标目录已存在，合并到: {}",
+                "[提示]".blue(),
+                target_path.display().to_string().cyan()
+            );
+            if merge_dir_into(source, &target_path, options, moves, identities) {
+                if !options.prune {
+                    moves.push((source.to_path_buf(), PathBuf::new()));
+                } else if let Err(e) = remove_dir(source) {
+                    eprintln!("{} 删除空目录失败 {:?}: {}", "[错误]".red(), source, e);
+                }
+                remove_empty_parents(source, options);
+            }
+            return;
+        }
+
+        // 文件冲突：添加后缀 (1), (2), ...
+        let is_free = |name: &str| !preview_exists(&dest_dir.join(name), options, moves);
+        match util::find_available_name(&dest_name, is_free) {
+            Some(new_name) => {
+                println!("  {} 目标已存在，使用新名称: {}", "[提示]".blue(), new_name);
+                target_path = dest_dir.join(new_name);
+            }
+            None => {
+                eprintln!(
+                    "{} 无法找到可用的移动目标（尝试了999个后缀）: {:?}",
+                    "[错误]".red(),
+                    source
+                );
+                return;
+            }
+        }
+    }
+
+    if !options.prune {
+        println!(
+            "  --> 预览：移动到 {}",
+            target_path.display().to_string().cyan()
+        );
+        moves.push((source.to_path_buf(), target_path));
+        return;
+    }
+    match rename_resolving_conflicts(source, target_path.clone(), options) {
+        Ok(final_path) => {
+            println!("--> {}", final_path.display().to_string().cyan());
+            moves.push((source.to_path_buf(), final_path));
+            remove_empty_parents(source, options);
+        }
+        Err(e) => {
+            eprintln!(
+                "{} 移动文件失败 {:?} -> {:?}: {}",
+                "[错误]".red(),
+                source,
+                target_path,
+                e
+            );
+        }
+    }
+}
+
+/**
+ * 条目被移走后，逐级删除留下的空目录，直到目标路径（不含）
+ */
+fn remove_empty_parents(path: &Path, options: &data::AppOptions) {
+    if !options.prune || !options.enable_prune_empty_dir {
+        return;
+    }
+    for dir in path.ancestors().skip(1) {
+        if dir == options.target_path || !dir.starts_with(&options.target_path) {
+            break;
+        }
+        if remove_dir(dir).is_err() {
+            break;
+        }
+        println!("  --> 删除空目录 {}", dir.display().to_string().cyan());
+    }
+}
+
+/**
+ * 将 source_dir 中的所有内容移动到 target_dir
+ *
+ * 返回 source_dir 是否已被清空（可以删除）。
+ */
+fn merge_dir_into(
+    source_dir: &Path,
+    target_dir: &Path,
+    options: &data::AppOptions,
+    moves: &mut MoveLog,
+    identities: &fsops::Identities,
+) -> bool {
+    let mut entries = match preview_children(source_dir, options, moves) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!(
+                "{} 无法读取目录内容 {:?}: {}",
+                "[错误]".red(),
+                source_dir,
+                e
+            );
+            return false;
+        }
+    };
+    entries.sort();
+
+    let mut all_moved = true;
+    for source_path in entries {
+        let filename = match source_path.file_name() {
+            Some(filename) => filename.to_os_string(),
+            None => continue,
+        };
+        // 受保护的目录留在原处
+        if moves
+            .real_path(&source_path)
+            .join(crate::NO_CLEANUP_MARKER)
+            .exists()
+        {
+            println!(
+                "  {} 受 {} 保护，不移动: {}",
+                "[提示]".blue(),
+                crate::NO_CLEANUP_MARKER,
+                source_path.display().to_string().cyan()
+            );
+            all_moved = false;
+            continue;
+        }
+        let mut target_path = target_dir.join(&filename);
+        let target_taken = preview_exists(&target_path, options, moves);
+
+        // 目录冲突：递归合并到已存在的目录
+        if target_taken
+            && options.merge_dirs
+            && target_path != source_dir
+            && preview_is_real_dir(&source_path, options, moves)
+            && preview_is_real_dir(&target_path, options, moves)
+        {
+            println!(
+                "  {} 目标目录已存在，合并到: {}",
+                "[提示]".blue(),
+                target_path.display().to_string().cyan()
+            );
+            if !merge_dir_into(&source_path, &target_path, options, moves, identities) {
+                all_moved = false;
+            } else if !options.prune {
+                moves.push((source_path, PathBuf::new()));
+            } else {
+                match remove_dir(&source_path) {
+                    Ok(_) => (),
+                    Err(e) => {
+                        all_moved = false;
+                        eprintln!("{} 删除空目录失败 {:?}: {}", "[错误]".red(), source_path, e);
+                    }
+                }
+            }
+            continue;
+        }
+
+        // 文件冲突：添加后缀 (1), (2), ...
+        if target_taken {
+            let original_name = filename.to_string_lossy();
+            let is_free = |name: &str| !preview_exists(&target_dir.join(name), options, moves);
+            match util::find_available_name(&original_name, is_free) {
+                Some(new_name) => {
+                    println!("  {} 目标已存在，使用新名称: {}", "[提示]".blue(), new_name);
+                    target_path = target_dir.join(new_name);
+                }
+                None => {
+                    eprintln!(
+                        "{} 无法找到可用的移动目标（尝试了999个后缀）: {:?}",
+                        "[错误]".red(),
+                        source_path
+                    );
+                    all_moved = false;
+                    continue;
+                }
+            }
+        }
+
+        if !options.prune {
+            println!(
+                "  --> 预览：移动 {} 到 {}",
+                source_path.display().to_string().cyan(),
+                target_path.display().to_string().cyan()
+            );
+            moves.push((source_path, target_path));
+            continue;
+        }
+        if !unchanged(&moves.real_path(&source_path), &source_path, identities) {
+            all_moved = false;
+            continue;
+        }
+        match rename_resolving_conflicts(&source_path, target_path.clone(), options) {
+            Ok(final_path) => {
+                println!(
+                    "  --> 移动 {} 到 {}",
+                    source_path.display().to_string().cyan(),
+                    final_path.display().to_string().cyan()
+                );
+                moves.push((source_path, final_path));
+            }
+            Err(e) => {
+                all_moved = false;
+                eprintln!(
+                    "{} 移动文件失败 {:?} -> {:?}: {}",
+                    "[错误]".red(),
+                    source_path,
+                    target_path,
+                    e
+                );
+            }
+        }
+    }
+    all_moved
+}
+
+/// 路径是否存在；预览模式下叠加之前的移动记录
+fn preview_exists(path: &Path, options: &data::AppOptions, moves: &MoveLog) -> bool {
+    if options.prune {
+        return util::path_exists(path);
+    }
+    let real_path = moves.real_path(path);
+    util::path_exists(&real_path) && moves.remap(&real_path) == path
+}
+
+/// 是否为真实目录；预览模式下叠加之前的移动记录
+fn preview_is_real_dir(path: &Path, options: &data::AppOptions, moves: &MoveLog) -> bool {
+    if options.prune {
+        return is_real_dir(path);
+    }
+    preview_exists(path, options, moves) && is_real_dir(&moves.real_path(path))
+}
+
+/// 列出目录内容；预览模式下叠加之前的移动记录
+fn preview_children(
+    dir: &Path,
+    options: &data::AppOptions,
+    moves: &MoveLog,
+) -> io::Result<Vec<PathBuf>> {
+    if options.prune {
+        return Ok(read_dir(dir)?.flatten().map(|entry| entry.path()).collect());
+    }
+    // 仍留在目录中的真实子项，以及之前移入的条目
+    let mut children: HashSet<PathBuf> = read_dir(moves.real_path(dir))?
+        .flatten()
+        .map(|entry| moves.remap(&entry.path()))
+        .filter(|path| path.parent() == Some(dir))
+        .collect();
+    if let Some(moved_in) = moves.children.get(dir) {
+        children.extend(moved_in.iter().cloned());
+    }
+    Ok(children.into_iter().collect())
+}
+
+/**
+ * 实际执行前确认 `path` 上的条目仍是扫描时位于 `scanned` 的那一个，否则输出警告并跳过。
+ * 没有记录的条目（如按 skip 规则跳过、没有扫描的条目）不检查
+ */
+fn unchanged(scanned: &Path, path: &Path, identities: &fsops::Identities) -> bool {
+    match identities.get(scanned) {
+        Some(identity) if !identity.matches(path) => {
+            eprintln!(
+                "{} 规划之后已被修改或替换，跳过: {:?}",
+                "[警告]".yellow(),
+                path
+            );
+            false
+        }
+        _ => true,
+    }
+}
+
+/**
+ * 不覆盖地重命名（跨文件系统时复制后删除），返回实际使用的目标路径
+ *
+ * 规划之后目标可能被其他进程抢先创建（如 aria2 仍在写入，或另一个清理进程），
+ * 此时（EEXIST）回到冲突处理，继续尝试下一个可用的后缀名称，绝不覆盖已有数据。
+ */
+fn rename_resolving_conflicts(
+    source: &Path,
+    mut target: PathBuf,
+    options: &data::AppOptions,
+) -> io::Result<PathBuf> {
+    loop {
+        match fsops::move_path(source, &target, options.verify_copy) {
+            Ok(()) => return Ok(target),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let (parent, name) = match (target.parent(), target.file_name()) {
+                    (Some(parent), Some(name)) => (parent, name.to_string_lossy().into_owned()),
+                    _ => return Err(e),
+                };
+                let is_free = |name: &str| !util::path_exists(&parent.join(name));
+                match util::find_available_name(&name, is_free) {
+                    Some(new_name) => {
+                        println!(
+                            "  {} 目标已被占用，使用新名称: {}",
+                            "[提示]".blue(),
+                            new_name
+                        );
+                        target = parent.join(new_name);
+                    }
+                    None => return Err(e),
+                }
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/**
+ * 记录执行计划会改变的目录（被删除、重命名或移动的条目所在的目录，被操作的目录自身，以及合并时写入的目录）的元数据，
+ * 用于执行后恢复访问时间和修改时间
+ */
+pub fn record_dir_times(
+    operations: &HashMap<PathBuf, (String, data::Operation)>,
+    options: &data::AppOptions,
+) -> Vec<(PathBuf, Metadata)> {
+    let mut dirs: HashSet<PathBuf> = HashSet::new();
+    for (path, (pattern, op)) in operations {
+        if *op == data::Operation::None {
+            continue;
+        }
+        dirs.extend(path.parent().map(Path::to_path_buf));
+        dirs.insert(path.clone());
+        if *op == data::Operation::Move {
+            // 移动后清理空的父目录会改变更上层的目录；创建目标目录会改变已存在的最近一级目录
+            dirs.extend(
+                path.ancestors()
+                    .skip(1)
+                    .take_while(|p| p.starts_with(&options.target_path))
+                    .map(Path::to_path_buf),
+            );
+            dirs.extend(
+                Path::new(pattern)
+                    .ancestors()
+                    .skip(1)
+                    .find(|p| is_real_dir(p))
+                    .map(Path::to_path_buf),
+            );
+            if options.merge_dirs && is_real_dir(path) && is_real_dir(Path::new(pattern)) {
+                dirs.insert(PathBuf::from(pattern));
+                collect_merge_targets(path, Path::new(pattern), &mut dirs);
+            }
+        }
+        if *op == data::Operation::MoveToParent && options.merge_dirs {
+            if let Some(parent) = path.parent() {
+                collect_merge_targets(path, parent, &mut dirs);
+            }
+        }
+    }
+    dirs.into_iter()
+        .filter_map(|dir| match dir.symlink_metadata() {
+            Ok(metadata) if metadata.is_dir() => Some((dir, metadata)),
+            _ => None,
+        })
+        .collect()
+}
+
+/**
+ * 合并目录时会写入内容的已有目录：`source_dir` 中的子目录在 `target_dir` 中有同名目录时递归合并
+ */
+fn collect_merge_targets(source_dir: &Path, target_dir: &Path, dirs: &mut HashSet<PathBuf>) {
+    let Ok(entries) = read_dir(source_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let source = entry.path();
+        let target = target_dir.join(entry.file_name());
+        if target != source_dir && is_real_dir(&source) && is_real_dir(&target) {
+            collect_merge_targets(&source, &target, dirs);
+            dirs.insert(target);
+        }
+    }
+}
+
+/**
+ * 恢复目录的访问时间和修改时间：按移动和重命名记录找到目录的新位置，从深到浅依次恢复，
+ * 已被删除的目录跳过
+ */
+pub fn restore_dir_times(mut dirs: Vec<(PathBuf, Metadata)>, moves: &[(PathBuf, PathBuf)]) {
+    dirs.sort_by_key(|(path, _)| Reverse(path.components().count()));
+    for (path, metadata) in dirs {
+        let path = util::remap_path(&path, moves);
+        if !is_real_dir(&path) {
+            continue;
+        }
+        if let Err(e) = fsops::set_times(&path, &metadata) {
+            eprintln!("{} 恢复目录时间失败 {:?}: {}", "[警告]".yellow(), path, e);
+        }
+    }
+}
+
+/**
+ * 目标是否只是源文件名称的大小写变体：名称忽略大小写后相同，且在（预览的）文件系统上指向同一个文件。
+ * 大小写不敏感的文件系统（exFAT、CIFS 等）上这样的目标看起来已存在，但实际就是源文件自身
+ */
+fn is_case_variant(
+    source: &Path,
+    target: &Path,
+    options: &data::AppOptions,
+    moves: &MoveLog,
+) -> bool {
+    let same_name = match (source.file_name(), target.file_name()) {
+        (Some(a), Some(b)) => {
+            a != b && a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+        }
+        _ => false,
+    };
+    if !same_name {
+        return false;
+    }
+    if options.prune {
+        util::is_same_file(source, target)
+    } else {
+        util::is_same_file(&moves.real_path(source), &moves.real_path(target))
+    }
+}
+
+/**
+ * 在目录中生成一个未被占用的临时名称
+ */
+fn temp_path_in(dir: &Path, is_taken: impl Fn(&Path) -> bool) -> PathBuf {
+    (1..)
+        .map(|counter| dir.join(format!(".fcr-tmp-{}-{}", std::process::id(), counter)))
+        .find(|path| !is_taken(path))
+        .unwrap()
+}
+
+/// 是否为真实目录（不是指向目录的符号链接）
+fn is_real_dir(path: &Path) -> bool {
+    path.symlink_metadata()
+        .map(|m| m.file_type().is_dir())
+        .unwrap_or(false)
+}
+//EOP
@@ -22,7 +22,7 @@
 //! - `foo/test?.txt` would match e.g. `foo/test1.txt` or `foo/test".txt`,
 //!   but not `foo/test/.txt`
 //! - `/etc/c[--9].conf` would match e.g. `/etc/c-.conf`, `/etc/c..conf`,
-//!    or `/etc/7.conf`, but not `/etc/c/.conf`
+//!   or `/etc/7.conf`, but not `/etc/c/.conf`
 //! - `linux-[0-9]*-{generic,aws}` would match `linux-5.2.27b1-generic`
 //!   and `linux-4.0.12-aws`, but not `linux-unsigned-5.2.27b1-generic`
 //!
@@ -106,9 +106,11 @@ struct ClassAccumulator {
 }
 
 /// The current state of the glob pattern parser.
-#[derive(Debug)]
+// We need Default so we can use mem::take() later.
+#[derive(Debug, Default)]
 enum State {
     /// The very start of the pattern.
+    #[default]
     Start,
     /// The end of the pattern, nothing more to do.
     End,
@@ -134,13 +136,6 @@ enum State {
     AlternateEscape(String, Vec<String>),
 }
 
-// We need this so we can use mem::take() later.
-impl Default for State {
-    fn default() -> Self {
-        Self::Start
-    }
-}
-
 /// Escape a character in a character class if necessary.
 /// This only escapes the backslash itself and the closing bracket.
 fn escape_in_class(chr: char) -> String {
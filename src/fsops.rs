@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs::{
+    create_dir, hard_link, read_dir, read_link, remove_file, rename, set_permissions, File,
+    OpenOptions,
+};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{symlink, FileExt, MetadataExt};
+use std::path::{Path, PathBuf};
+
+use colored::*;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::pmatcher;
+use crate::util;
+
+/// 超过此大小的文件在跨文件系统复制时显示进度条
+const LARGE_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/**
+ * 规划时记录的条目身份：设备、inode、大小和修改时间，执行前用于确认仍是同一个文件
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Identity {
+    dev: u64,
+    ino: u64,
+    is_dir: bool,
+    size: u64,
+    mtime: (i64, i64),
+}
+
+/// 扫描时记录的条目身份，键为扫描时的路径
+pub type Identities = HashMap<PathBuf, Identity>;
+
+impl Identity {
+    /// 不跟随符号链接
+    pub fn of(path: &Path) -> io::Result<Identity> {
+        let metadata = path.symlink_metadata()?;
+        Ok(Identity {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+            mtime: (metadata.mtime(), metadata.mtime_nsec()),
+        })
+    }
+
+    /**
+     * 路径上的条目是否仍是规划时的那一个（没有被修改或替换）
+     *
+     * 目录只比较设备和 inode（其中的条目被删除、重命名时大小和修改时间会变）。
+     * 之前的操作可能已将条目跨文件系统移动（复制后 inode 不同），设备不同时只比较大小和修改时间
+     */
+    pub fn matches(&self, path: &Path) -> bool {
+        let Ok(current) = Identity::of(path) else {
+            return false;
+        };
+        if current.is_dir != self.is_dir {
+            return false;
+        }
+        if current.dev == self.dev && current.ino != self.ino {
+            return false;
+        }
+        self.is_dir || (current.size == self.size && current.mtime == self.mtime)
+    }
+}
+
+/**
+ * 不覆盖目标的重命名
+ *
+ * Linux 上使用 renameat2(RENAME_NOREPLACE) 原子地完成；文件系统不支持时，
+ * 文件退回到 link + unlink（link 在目标存在时同样原子地失败），
+ * 两者都不可用时（如目录，或不支持硬链接的文件系统）才退回到先检查再 rename。
+ * 目标已存在时返回 `ErrorKind::AlreadyExists`，由调用方重新处理冲突。
+ */
+pub fn rename_noreplace(from: &Path, to: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    match renameat2_noreplace(from, to) {
+        Err(e) if is_unsupported(&e) => (),
+        result => return result,
+    }
+
+    if !from.symlink_metadata()?.is_dir() {
+        match hard_link(from, to) {
+            Ok(()) => return remove_file(from),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(e),
+            Err(_) => (), // 不支持硬链接，继续退回
+        }
+    }
+
+    if util::path_exists(to) {
+        return Err(io::Error::from(io::ErrorKind::AlreadyExists));
+    }
+    rename(from, to)
+}
+
+/**
+ * 文件内容是否全为零字节（空文件也返回 true）
+ *
+ * 只读取有数据的区间（Linux 上用 SEEK_DATA/SEEK_HOLE 跳过空洞和预分配的区间），
+ * 遇到第一个非零字节即返回，因此普通文件通常只需读取第一个块。
+ */
+pub fn is_zero_filled(path: &Path) -> io::Result<bool> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut offset = 0;
+    while offset < len {
+        let Some((start, end)) = next_data_region(&file, offset, len)? else {
+            break;
+        };
+        let mut position = start;
+        while position < end {
+            let size = buffer.len().min((end - position) as usize);
+            let bytes_read = file.read_at(&mut buffer[..size], position)?;
+            if bytes_read == 0 {
+                return Ok(true); // 文件在检查过程中被截断
+            }
+            if buffer[..bytes_read].iter().any(|&b| b != 0) {
+                return Ok(false);
+            }
+            position += bytes_read as u64;
+        }
+        offset = end;
+    }
+    Ok(true)
+}
+
+/**
+ * 从 `offset` 开始的下一段数据区间 [start, end)，之后没有数据时返回 None；
+ * 不支持 SEEK_DATA 时整个剩余部分视为数据
+ */
+#[cfg(target_os = "linux")]
+fn next_data_region(file: &File, offset: u64, len: u64) -> io::Result<Option<(u64, u64)>> {
+    let fd = file.as_raw_fd();
+    let start = unsafe { libc::lseek(fd, offset as libc::off_t, libc::SEEK_DATA) };
+    if start < 0 {
+        let e = io::Error::last_os_error();
+        return match e.raw_os_error() {
+            Some(libc::ENXIO) => Ok(None),
+            Some(libc::EINVAL) => Ok(Some((offset, len))),
+            _ => Err(e),
+        };
+    }
+    let end = unsafe { libc::lseek(fd, start, libc::SEEK_HOLE) };
+    if end < 0 {
+        return Ok(Some((start as u64, len)));
+    }
+    Ok(Some((start as u64, (end as u64).min(len))))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn next_data_region(_file: &File, offset: u64, len: u64) -> io::Result<Option<(u64, u64)>> {
+    Ok(Some((offset, len)))
+}
+
+/**
+ * 移动文件或目录，不覆盖目标
+ *
+ * 跨文件系统（EXDEV）时退回到复制后删除：保留时间戳、权限、所有者（权限允许时）和扩展属性，
+ * 校验复制后的大小（`verify_hash` 时再校验 MD5）后才删除源文件；复制失败时清理已复制的部分。
+ */
+pub fn move_path(from: &Path, to: &Path, verify_hash: bool) -> io::Result<()> {
+    match rename_noreplace(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            println!(
+                "  {} 跨文件系统移动，复制后删除源: {}",
+                "[提示]".blue(),
+                from.display()
+            );
+            if let Err(e) = copy_entry(from, to, verify_hash) {
+                // 目标已存在时不是我们创建的，不能清理
+                if e.kind() != io::ErrorKind::AlreadyExists && util::path_exists(to) {
+                    let _ = util::remove_path(to.to_path_buf());
+                }
+                return Err(e);
+            }
+            util::remove_path(from.to_path_buf())
+        }
+        result => result,
+    }
+}
+
+/**
+ * 递归复制文件、目录或符号链接，目标必须不存在
+ */
+fn copy_entry(from: &Path, to: &Path, verify_hash: bool) -> io::Result<()> {
+    let metadata = from.symlink_metadata()?;
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        symlink(read_link(from)?, to)?;
+    } else if file_type.is_dir() {
+        create_dir(to)?;
+        for entry in read_dir(from)? {
+            let entry = entry?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()), verify_hash)?;
+        }
+    } else if file_type.is_file() {
+        copy_file(from, to, &metadata, verify_hash)?;
+    } else {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("不支持复制特殊文件: {}", from.display()),
+        ));
+    }
+    copy_metadata(from, to, &metadata)
+}
+
+fn copy_file(
+    from: &Path,
+    to: &Path,
+    metadata: &std::fs::Metadata,
+    verify_hash: bool,
+) -> io::Result<()> {
+    let mut reader = File::open(from)?;
+    let mut writer = OpenOptions::new().write(true).create_new(true).open(to)?;
+    let len = metadata.len();
+    if len >= LARGE_FILE_SIZE {
+        let progress = ProgressBar::new(len);
+        progress.set_style(
+            ProgressStyle::default_bar()
+                .template(
+                    "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec})\n{msg}",
+                )
+                .unwrap()
+                .progress_chars("█▓▒░ "),
+        );
+        progress.set_message(format!("复制: {}", from.display()));
+        io::copy(&mut progress.wrap_read(reader), &mut writer)?;
+        progress.finish_and_clear();
+    } else {
+        io::copy(&mut reader, &mut writer)?;
+    }
+    writer.sync_all()?;
+
+    let copied = writer.metadata()?.len();
+    if copied != len {
+        return Err(io::Error::other(format!(
+            "复制后大小不一致 {}: {} != {}",
+            to.display(),
+            copied,
+            len
+        )));
+    }
+    if verify_hash && pmatcher::calculate_md5(from)? != pmatcher::calculate_md5(to)? {
+        return Err(io::Error::other(format!(
+            "复制后 MD5 不一致: {}",
+            to.display()
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * 复制所有者、权限、扩展属性和访问/修改时间（不跟随符号链接）
+ *
+ * 所有者和部分扩展属性命名空间需要特权，无权限时忽略；时间最后设置，避免被前面的操作改变。
+ */
+fn copy_metadata(from: &Path, to: &Path, metadata: &std::fs::Metadata) -> io::Result<()> {
+    let c_to = path_to_cstring(to)?;
+
+    // 先 chown 再 chmod：chown 会清除 setuid/setgid 位
+    if unsafe { libc::lchown(c_to.as_ptr(), metadata.uid(), metadata.gid()) } != 0 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::EPERM) {
+            return Err(e);
+        }
+    }
+    if !metadata.file_type().is_symlink() {
+        set_permissions(to, metadata.permissions())?;
+    }
+
+    #[cfg(target_os = "linux")]
+    copy_xattrs(from, to)?;
+
+    set_times(to, metadata)
+}
+
+/**
+ * 按元数据设置访问时间和修改时间（纳秒精度，不跟随符号链接）
+ */
+pub fn set_times(path: &Path, metadata: &std::fs::Metadata) -> io::Result<()> {
+    let c_path = path_to_cstring(path)?;
+    let times = [
+        libc::timespec {
+            tv_sec: metadata.atime() as libc::time_t,
+            tv_nsec: metadata.atime_nsec() as _,
+        },
+        libc::timespec {
+            tv_sec: metadata.mtime() as libc::time_t,
+            tv_nsec: metadata.mtime_nsec() as _,
+        },
+    ];
+    let ret = unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            c_path.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn copy_xattrs(from: &Path, to: &Path) -> io::Result<()> {
+    let c_from = path_to_cstring(from)?;
+    let c_to = path_to_cstring(to)?;
+
+    let size = unsafe { libc::llistxattr(c_from.as_ptr(), std::ptr::null_mut(), 0) };
+    if size < 0 {
+        let e = io::Error::last_os_error();
+        return if is_xattr_unsupported(&e) {
+            Ok(())
+        } else {
+            Err(e)
+        };
+    }
+    if size == 0 {
+        return Ok(());
+    }
+    let mut names = vec![0u8; size as usize];
+    let size = unsafe {
+        libc::llistxattr(
+            c_from.as_ptr(),
+            names.as_mut_ptr() as *mut libc::c_char,
+            names.len(),
+        )
+    };
+    if size < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    for name in names[..size as usize]
+        .split(|&b| b == 0)
+        .filter(|n| !n.is_empty())
+    {
+        let c_name = CString::new(name)?;
+        let value_size =
+            unsafe { libc::lgetxattr(c_from.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0) };
+        if value_size < 0 {
+            continue;
+        }
+        let mut value = vec![0u8; value_size as usize];
+        let value_size = unsafe {
+            libc::lgetxattr(
+                c_from.as_ptr(),
+                c_name.as_ptr(),
+                value.as_mut_ptr() as *mut libc::c_void,
+                value.len(),
+            )
+        };
+        if value_size < 0 {
+            continue;
+        }
+        let ret = unsafe {
+            libc::lsetxattr(
+                c_to.as_ptr(),
+                c_name.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value_size as usize,
+                0,
+            )
+        };
+        if ret != 0 {
+            // 忽略目标不支持或需要特权的命名空间（如 trusted.*、security.*）
+            let e = io::Error::last_os_error();
+            if !is_xattr_unsupported(&e) {
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn is_xattr_unsupported(e: &io::Error) -> bool {
+    matches!(
+        e.raw_os_error(),
+        Some(libc::ENOTSUP) | Some(libc::EPERM) | Some(libc::EACCES)
+    )
+}
+
+fn path_to_cstring(path: &Path) -> io::Result<CString> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
+
+#[cfg(target_os = "linux")]
+fn renameat2_noreplace(from: &Path, to: &Path) -> io::Result<()> {
+    let c_from = path_to_cstring(from)?;
+    let c_to = path_to_cstring(to)?;
+    let ret = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            c_from.as_ptr(),
+            libc::AT_FDCWD,
+            c_to.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// renameat2 不可用：内核不支持（ENOSYS）或文件系统不支持该标志（EINVAL）
+#[cfg(target_os = "linux")]
+fn is_unsupported(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::ENOSYS) | Some(libc::EINVAL))
+}
+//EOP
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use indicatif::HumanBytes;
+
+use crate::data::Operation;
+use crate::pconfig::LimitsConfig;
+
+/// 默认的删除文件数占比上限（百分比）
+pub const DEFAULT_MAX_PERCENT: f64 = 90.0;
+/// 默认的单个文件大小上限：4 GiB
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 4 << 30;
+
+/// 删除计划的统计：删除的文件数、总大小，以及超过单个文件大小上限的文件
+#[derive(Debug, Default)]
+pub struct DeletionSummary {
+    pub files: u64,
+    pub bytes: u64,
+    pub total_files: u64,
+    pub large_files: Vec<(PathBuf, u64)>,
+}
+
+/**
+ * 统计删除计划（包括随父目录删除的条目）。`total_files` 是扫描到的文件总数（不含目录），
+ * `allow_large` 判断文件是否被规则允许超过单个文件的大小上限
+ */
+pub fn summarize(
+    operations: &HashMap<PathBuf, (String, Operation)>,
+    total_files: u64,
+    config: &LimitsConfig,
+    allow_large: impl Fn(&str) -> bool,
+) -> DeletionSummary {
+    let mut summary = DeletionSummary {
+        total_files,
+        ..Default::default()
+    };
+    for (path, (_, op)) in operations {
+        if *op != Operation::Delete {
+            continue;
+        }
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+        if metadata.is_dir() {
+            continue;
+        }
+        summary.files += 1;
+        summary.bytes += metadata.len();
+        if let Some(max_file_size) = config.max_file_size {
+            let allowed = path
+                .file_name()
+                .is_some_and(|name| allow_large(&name.to_string_lossy()));
+            if metadata.len() > max_file_size && !allowed {
+                summary.large_files.push((path.clone(), metadata.len()));
+            }
+        }
+    }
+    summary.large_files.sort();
+    summary
+}
+
+/**
+ * 检查删除计划是否超出安全限制，返回每一项超出的说明；为空表示没有超出
+ */
+pub fn check(summary: &DeletionSummary, config: &LimitsConfig) -> Vec<String> {
+    let mut violations = Vec::new();
+    if let Some(max_files) = config.max_files {
+        if summary.files > max_files {
+            violations.push(format!(
+                "删除 {} 个文件（上限 {}）",
+                summary.files, max_files
+            ));
+        }
+    }
+    if let Some(max_bytes) = config.max_bytes {
+        if summary.bytes > max_bytes {
+            violations.push(format!(
+                "删除 {}（上限 {}）",
+                HumanBytes(summary.bytes),
+                HumanBytes(max_bytes)
+            ));
+        }
+    }
+    if let Some(max_percent) = config.max_percent {
+        if summary.total_files > 0 {
+            let percent = summary.files as f64 * 100.0 / summary.total_files as f64;
+            if percent > max_percent {
+                violations.push(format!(
+                    "删除 {:.1}% 的文件（{}/{}，上限 {}%）",
+                    percent, summary.files, summary.total_files, max_percent
+                ));
+            }
+        }
+    }
+    if let Some(max_file_size) = config.max_file_size {
+        for (path, size) in &summary.large_files {
+            violations.push(format!(
+                "删除大文件 {:?}（{}，上限 {}）",
+                path,
+                HumanBytes(*size),
+                HumanBytes(max_file_size)
+            ));
+        }
+    }
+    violations
+}
+
+/**
+ * 解析大小：字节数，或带单位的字符串（`500M`、`1.5G`、`10GiB`，按 1024 进制）
+ */
+pub fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let unit = unit
+        .strip_suffix("IB")
+        .or_else(|| unit.strip_suffix('B'))
+        .unwrap_or(&unit);
+    let multiplier: u64 = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        "T" => 1 << 40,
+        _ => return None,
+    };
+    Some((number * multiplier as f64) as u64)
+}
+//EOP
@@ -0,0 +1,88 @@
+use std::fs::{self, DirBuilder, File, OpenOptions, TryLockError};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+use md5::{Digest, Md5};
+
+/// 锁文件目录名（位于当前用户的运行时目录中，不在被清理的目录树里）
+const LOCK_DIR_NAME: &str = "file-clean-rust-locks";
+
+/**
+ * 目录树锁：目标路径上的排他锁，以及各级上级目录上的共享锁。
+ * 同一用户的其他进程正在清理同一目录树、上级目录或下级目录时无法获得；进程退出时自动释放
+ */
+#[derive(Debug)]
+pub struct TreeLock {
+    _files: Vec<File>,
+}
+
+/**
+ * 获取目标路径的目录树锁。自上而下依次加锁：上级目录加共享锁（兄弟目录可以同时清理），
+ * 目标路径加排他锁，因此清理上级目录的进程与清理下级目录的进程互斥。
+ * `wait` 为 false 时不等待，锁被占用时返回 None
+ */
+pub fn acquire(target_path: &Path, wait: bool) -> io::Result<Option<TreeLock>> {
+    let lock_dir = lock_dir()?;
+    let mut ancestors: Vec<&Path> = target_path.ancestors().collect();
+    ancestors.reverse();
+    let mut files = Vec::with_capacity(ancestors.len());
+    for path in ancestors {
+        let file = open_lock_file(&lock_dir, path)?;
+        let exclusive = path == target_path;
+        let result = match (exclusive, wait) {
+            (true, true) => file.lock().map_err(TryLockError::Error),
+            (false, true) => file.lock_shared().map_err(TryLockError::Error),
+            (true, false) => file.try_lock(),
+            (false, false) => file.try_lock_shared(),
+        };
+        match result {
+            Ok(()) => files.push(file),
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => return Err(e),
+        }
+    }
+    Ok(Some(TreeLock { _files: files }))
+}
+
+/**
+ * 锁文件目录，每个用户一个：优先使用 `$XDG_RUNTIME_DIR`，否则是系统临时目录中以用户 ID 结尾的目录。
+ * 目录必须是当前用户所有、其他用户无权访问的真实目录（不是符号链接），
+ * 否则其他用户可以预先占住锁文件，使清理进程一直等待
+ */
+fn lock_dir() -> io::Result<PathBuf> {
+    let uid = unsafe { libc::getuid() };
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from) {
+        Some(runtime_dir) if runtime_dir.is_absolute() => runtime_dir.join(LOCK_DIR_NAME),
+        _ => std::env::temp_dir().join(format!("{}-{}", LOCK_DIR_NAME, uid)),
+    };
+    match DirBuilder::new().mode(0o700).create(&dir) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+        Err(e) => return Err(e),
+    }
+    let metadata = fs::symlink_metadata(&dir)?;
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("锁文件目录不是当前用户私有的目录: {:?}", dir),
+        ));
+    }
+    Ok(dir)
+}
+
+/// 每个目录一个锁文件，以路径的 MD5 命名
+fn open_lock_file(lock_dir: &Path, path: &Path) -> io::Result<File> {
+    let lock_path = lock_dir.join(format!(
+        "{:x}.lock",
+        Md5::digest(path.as_os_str().as_bytes())
+    ));
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .mode(0o600)
+        .open(&lock_path)
+}
+//EOP
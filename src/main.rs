@@ -1,21 +1,39 @@
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
-use std::fs::rename;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use colored::*;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
+mod bencode;
 mod cli;
 mod data;
+mod download;
+mod encoding;
+mod executor;
 mod fnmatch_regex;
+mod fsops;
+mod limits;
+mod lock;
+mod manifest;
 mod p2tree;
 mod pconfig;
 mod pmatcher;
+mod release;
+mod sanitize;
+mod sidecar;
+mod template;
+mod torrent;
 mod tprint;
 mod util;
+mod zhconv;
+
+/// 受保护目录树的标记文件：所在目录及其中的内容不做任何操作
+const NO_CLEANUP_MARKER: &str = ".nocleanup";
 
 fn main() -> std::io::Result<()> {
     let app_options = cli::parse()?;
@@ -24,13 +42,69 @@ fn main() -> std::io::Result<()> {
         println!("{:#?}", app_options);
     }
 
-    let pattern_matcher = Arc::new(pmatcher::PatternMatcher::from_config_file(
-        &app_options.config_file,
-    ));
+    let mut pattern_matcher = pmatcher::PatternMatcher::from_config_file(&app_options.config_file);
+    pattern_matcher.add_exclude_patterns(&app_options.exclude);
+    let pattern_matcher = Arc::new(pattern_matcher);
     if app_options.is_debug_mode() {
         println!("{:#?}", pattern_matcher);
     }
 
+    // 拒绝清理系统目录、用户主目录和挂载点，除非明确确认
+    if let Some(reason) = util::dangerous_root(&app_options.target_path) {
+        if !app_options.allow_dangerous_root {
+            eprintln!(
+                "{} 目标路径 {:?} 是{}，拒绝执行；确认无误请使用 --i-know-what-im-doing",
+                "[错误]".red(),
+                app_options.target_path,
+                reason
+            );
+            std::process::exit(1);
+        }
+        eprintln!(
+            "{} 目标路径 {:?} 是{}",
+            "[警告]".yellow(),
+            app_options.target_path,
+            reason
+        );
+    }
+
+    // 目标路径位于受保护的目录树中（自身或上级目录中有 .nocleanup 标记文件）
+    if let Some(dir) = app_options
+        .target_path
+        .ancestors()
+        .find(|dir| dir.join(NO_CLEANUP_MARKER).exists())
+    {
+        println!(
+            "{} {:#?} 中有 {} 标记文件，不做任何操作",
+            "[提示]".blue(),
+            dir,
+            NO_CLEANUP_MARKER
+        );
+        return Ok(());
+    }
+
+    // 执行时锁定目录树，避免多个清理进程同时处理同一目录树（或其上级、下级目录）
+    let _tree_lock = if app_options.prune {
+        let wait = app_options.on_locked == data::LockMode::Wait;
+        match lock::acquire(&app_options.target_path, false)? {
+            Some(tree_lock) => Some(tree_lock),
+            None if wait => {
+                println!("{} 其他清理进程正在处理此目录树，等待...", "[提示]".blue());
+                lock::acquire(&app_options.target_path, true)?
+            }
+            None if app_options.on_locked == data::LockMode::Skip => {
+                println!("{} 其他清理进程正在处理此目录树，跳过", "[提示]".blue());
+                return Ok(());
+            }
+            None => {
+                eprintln!("{} 其他清理进程正在处理此目录树", "[错误]".red());
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
     println!("正在扫描文件...");
     let spinner = ProgressBar::new_spinner();
     spinner.set_style(
@@ -44,6 +118,13 @@ fn main() -> std::io::Result<()> {
 
     // 仅扫描一次文件系统，收集所有路径
     let mut file_count = 0;
+    // 按 skip 规则跳过的条目，以及有 .nocleanup 标记文件的受保护目录
+    let mut skipped: Vec<PathBuf> = Vec::new();
+    let mut protected: Vec<PathBuf> = Vec::new();
+    // 位于其他文件系统上的目录（--one-file-system），以及跳过的特殊文件
+    let mut other_fs: Vec<PathBuf> = Vec::new();
+    let mut special: Vec<(PathBuf, &str)> = Vec::new();
+    let root_dev = std::fs::metadata(&app_options.target_path)?.dev();
     let entries: Vec<_> = WalkDir::new(&app_options.target_path)
         .sort_by(|a, b| {
             let depth_a = a.depth();
@@ -59,9 +140,39 @@ fn main() -> std::io::Result<()> {
                 .then(a.file_name().cmp(b.file_name()))
         })
         .into_iter()
-        .filter_entry(|e| !app_options.skip_parent_tmp || util::is_not_hidden(e))
+        .filter_entry(|e| {
+            if e.depth() == 0 {
+                return true;
+            }
+            let name = encoding::escape_os_str(e.file_name());
+            if pattern_matcher.match_skip(&name, app_options.enable_skip) {
+                skipped.push(e.path().to_path_buf());
+                return false;
+            }
+            if e.file_type().is_dir() && e.path().join(NO_CLEANUP_MARKER).exists() {
+                skipped.push(e.path().to_path_buf());
+                protected.push(e.path().to_path_buf());
+                return false;
+            }
+            if app_options.one_file_system
+                && e.file_type().is_dir()
+                && e.metadata().is_ok_and(|m| m.dev() != root_dev)
+            {
+                skipped.push(e.path().to_path_buf());
+                other_fs.push(e.path().to_path_buf());
+                return false;
+            }
+            if !app_options.include_special_files {
+                if let Some(kind) = util::special_file_kind(&e.file_type()) {
+                    skipped.push(e.path().to_path_buf());
+                    special.push((e.path().to_path_buf(), kind));
+                    return false;
+                }
+            }
+            true
+        })
         .filter_map(|e| {
-            if let Ok(_) = &e {
+            if e.is_ok() {
                 file_count += 1;
                 if file_count % 1000 == 0 {
                     spinner.set_message(format!("已扫描 {} 个文件...", file_count));
@@ -71,21 +182,116 @@ fn main() -> std::io::Result<()> {
         })
         .collect();
     spinner.finish_with_message(format!("扫描完成，共 {} 个文件", file_count));
+    for dir in &protected {
+        println!("{} {:#?} 受 {} 保护，已跳过", "[提示]".blue(), dir, NO_CLEANUP_MARKER);
+    }
+    for dir in &other_fs {
+        println!("{} {:#?} 位于其他文件系统，已跳过", "[提示]".blue(), dir);
+    }
+    if app_options.verbose > 0 {
+        for (path, kind) in &special {
+            println!("{} {:#?} 是{}，已跳过", "[提示]".blue(), path, kind);
+        }
+        for path in skipped.iter().filter(|path| {
+            !protected.contains(path)
+                && !other_fs.contains(path)
+                && !special.iter().any(|(special, _)| special == *path)
+        }) {
+            println!("{} {:#?} 匹配 skip 规则，已跳过", "[提示]".blue(), path);
+        }
+    }
+
+    // 展开冗余目录规则：命令行选项优先于配置文件
+    let flatten_rule = match app_options.flatten {
+        Some(false) => None,
+        Some(true) => Some(pattern_matcher.flatten.clone().unwrap_or_default()),
+        None => pattern_matcher.flatten.clone(),
+    };
+
+    let paths: Vec<PathBuf> = entries.iter().map(|e| e.path().to_path_buf()).collect();
+
+    // 仍在下载中的条目（及其所在目录）不做任何操作
+    let in_progress = match &pattern_matcher.in_progress {
+        Some(config) => download::find_in_progress(&paths, &app_options.target_path, config),
+        None => Vec::new(),
+    };
+    for (path, reason) in &in_progress {
+        println!("{} {:#?} 已跳过：正在下载（{}）", "[提示]".blue(), path, reason);
+    }
+    let in_progress_ref = &in_progress;
+    // 被跳过的条目、其中的内容，以及包含它们的目录（移动或删除会影响正在下载的文件）
+    let is_in_progress = |path: &Path| {
+        in_progress_ref
+            .iter()
+            .any(|(root, _)| path.starts_with(root) || root.starts_with(path))
+    };
+
+    // 解析种子文件：得到权威的垃圾文件列表（填充文件、外来文件），并校验内容
+    let torrents: Vec<_> = if app_options.torrent || app_options.verify {
+        torrent::find_torrent_files(&paths, &app_options.target_path)
+            .into_iter()
+            .filter_map(|path| match torrent::Torrent::from_file(&path) {
+                Ok(parsed) => Some((path, parsed)),
+                Err(e) => {
+                    eprintln!("{} 无法解析种子文件 {:?}: {}", "[警告]".yellow(), path, e);
+                    None
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    // 校验失败且要求严格校验时，不清理这些内容根目录
+    let blocked_roots = if app_options.verify {
+        verify_torrents(&torrents, &app_options)
+    } else {
+        Vec::new()
+    };
+
+    let torrent_junk = if app_options.enable_deletion && app_options.torrent {
+        torrent::plan_junk(
+            &torrents,
+            &paths,
+            &app_options.target_path,
+            app_options.torrent_foreign,
+        )
+    } else {
+        HashMap::new()
+    };
+    let torrent_junk_ref = &torrent_junk;
+
+    // 校验 .sfv / .md5 / .sha256 校验文件，校验通过的（按规则保留的除外）作为垃圾文件删除
+    let manifests = if app_options.verify_manifest {
+        verify_manifests(&paths)
+    } else {
+        Vec::new()
+    };
+    let manifest_junk: HashMap<PathBuf, String> = manifests
+        .iter()
+        .filter(|(parsed, passed)| {
+            *passed
+                && !parsed.path.file_name().is_some_and(|name| {
+                    pattern_matcher.match_keep_manifest(&encoding::escape_os_str(name))
+                })
+        })
+        .map(|(parsed, _)| (parsed.path.clone(), "<MANIFEST_VERIFIED>".to_string()))
+        .collect();
+    let manifest_junk_ref = &manifest_junk;
 
     // 并行处理文件信息
     let options_ref = &app_options;
     let matcher_ref = &pattern_matcher;
+    let flatten_ref = &flatten_rule;
+    // 模板重命名的解析结果（逐个文件报告通过/失败）
+    let template_report: Mutex<Vec<(PathBuf, Result<String, String>)>> = Mutex::new(Vec::new());
+    let template_report_ref = &template_report;
+    // 规划时每个条目的身份（设备、inode、大小、修改时间），执行前确认没有被修改或替换
+    let identities: Mutex<HashMap<PathBuf, fsops::Identity>> = Mutex::new(HashMap::new());
+    let identities_ref = &identities;
 
     println!("正在处理文件...");
-    let process_bar = ProgressBar::new(entries.len() as u64);
-    process_bar.set_style(
-        ProgressStyle::default_bar()
-            .template(
-                "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}\n{msg}",
-            )
-            .unwrap()
-            .progress_chars("█▓▒░ "),
-    );
+    let process_bar = util::progress_bar(entries.len() as u64);
 
     let file_info_results: Vec<_> = entries
         .par_iter()
@@ -95,74 +301,229 @@ fn main() -> std::io::Result<()> {
             process_bar.inc(1);
             // 显示当前处理的文件名
             if let Some(name) = filepath.file_name().and_then(|n| n.to_str()) {
-                if process_bar.position() % 100 == 0 {
+                if process_bar.position().is_multiple_of(100) {
                     process_bar.set_message(format!("处理: {}", name));
                 }
             }
 
-            // 处理无效文件名：输出警告并跳过
+            if is_in_progress(filepath) {
+                return Some((
+                    filepath.to_path_buf(),
+                    ("".to_string(), data::Operation::None),
+                ));
+            }
+
+            // 无效 UTF-8 的文件名用转义形式（\xNN）匹配规则
             let filename = match entry.file_name().to_str() {
-                Some(name) => name,
-                None => {
-                    eprintln!("{} 跳过无效文件名: {:?}", "[警告]".yellow(), filepath);
-                    return None; // 跳过这个条目
-                }
+                Some(name) => Cow::Borrowed(name),
+                None => Cow::Owned(encoding::escape_os_str(entry.file_name())),
             };
+            let filename: &str = &filename;
+
+            // 在匹配（特别是计算哈希）之前记录身份
+            if let Ok(identity) = fsops::Identity::of(filepath) {
+                identities_ref
+                    .lock()
+                    .unwrap()
+                    .insert(filepath.to_path_buf(), identity);
+            }
 
-            // 检查是否需要删除
-            if options_ref.enable_deletion {
+            // 检查是否需要删除（目标路径自身从不删除）
+            if options_ref.enable_deletion && entry.depth() > 0 {
                 let (mut matched, mut pattern) = matcher_ref.match_remove_pattern(filename);
                 if matched {
                     let p = pattern.unwrap();
                     return Some((filepath.to_path_buf(), (p, data::Operation::Delete)));
                 } else if options_ref.enable_hash_matching {
-                    (matched, pattern) = matcher_ref.match_remove_hash(filepath.to_str().unwrap());
+                    (matched, pattern) = matcher_ref.match_remove_hash(filepath);
                     if matched {
                         let p = pattern.unwrap();
                         return Some((filepath.to_path_buf(), (p, data::Operation::Delete)));
                     }
                 }
+                if let Some(reason) = torrent_junk_ref.get(filepath) {
+                    return Some((filepath.to_path_buf(), (reason.clone(), data::Operation::Delete)));
+                }
+                if let Some(reason) = manifest_junk_ref.get(filepath) {
+                    return Some((filepath.to_path_buf(), (reason.clone(), data::Operation::Delete)));
+                }
+                // 按内容判断：空文件、全为零字节的文件（如未按名称识别的填充文件、残留的预分配文件）
+                if let Some(reason) =
+                    matcher_ref.match_remove_content(filepath, &options_ref.target_path)
+                {
+                    return Some((filepath.to_path_buf(), (reason, data::Operation::Delete)));
+                }
             }
 
-            // 检查是否需要重命名
-            if options_ref.enable_renaming {
-                let new_filename = matcher_ref.clean_filename(filename);
-                if new_filename != filename {
-                    // 检查是否是目录且清理结果为空（只保留路径部分，文件名为空）
-                    if filepath.is_dir() {
-                        let cleaned_name = PathBuf::from(&new_filename)
-                            .file_name()
-                            .map(|n| n.to_string_lossy().to_string())
-                            .unwrap_or_default();
-
-                        if cleaned_name.is_empty() {
-                            // 目录名被完全清理，需要移动内容到父目录
+            // 检查是否为冗余的包装目录：只包含一个子目录（或一个文件），根目录除外
+            if let Some(rule) = flatten_ref {
+                if entry.depth() > 0 && filepath.is_dir() && !filepath.is_symlink() {
+                    if let Some(child) = util::single_child(filepath) {
+                        let should_flatten = if child.is_dir() && !child.is_symlink() {
+                            !rule.same_name
+                                || child.file_name().map(encoding::escape_os_str).is_some_and(|n| {
+                                    matcher_ref.clean_filename(&n) == matcher_ref.clean_filename(filename)
+                                })
+                        } else {
+                            rule.single_file
+                        };
+                        if should_flatten {
                             return Some((
                                 filepath.to_path_buf(),
-                                ("".to_string(), data::Operation::MoveToParent),
+                                ("<FLATTEN>".to_string(), data::Operation::MoveToParent),
                             ));
                         }
                     }
+                }
+            }
+
+            // 无效 UTF-8 的文件名先按候选编码转码，作为重命名的起点；无法转码时不重命名也不整理
+            // 有效的文件名在配置了 `repair_encoding` 时尝试修复乱码
+            let rename_source = match entry.file_name().to_str() {
+                Some(name) if options_ref.enable_renaming => {
+                    match matcher_ref.repair_filename(name) {
+                        Some((repaired, misdecoding, encoding_name)) => {
+                            println!(
+                                "{} 修复乱码 ({} -> {}): {:?} ==> {}",
+                                "[信息]".blue(),
+                                misdecoding,
+                                encoding_name,
+                                filepath,
+                                repaired
+                            );
+                            Some(Cow::Owned(repaired))
+                        }
+                        None => Some(Cow::Borrowed(name)),
+                    }
+                }
+                Some(name) => Some(Cow::Borrowed(name)),
+                None if options_ref.enable_renaming => {
+                    match matcher_ref.transcode_filename(entry.file_name()) {
+                        Some((name, encoding_name)) => {
+                            println!(
+                                "{} 文件名转码 ({}): {:?} ==> {}",
+                                "[信息]".blue(),
+                                encoding_name,
+                                filepath,
+                                name
+                            );
+                            Some(Cow::Owned(name))
+                        }
+                        // 未配置 `transcode` 时保留原名，不输出警告
+                        None => {
+                            if !matcher_ref.transcode_encodings.is_empty() {
+                                eprintln!("{} 无法转码文件名: {:?}", "[警告]".yellow(), filepath);
+                            }
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
 
-                    return Some((
-                        filepath.to_path_buf(),
-                        (new_filename, data::Operation::Rename),
-                    ));
+            // 计算新文件名：文件优先使用模板重命名规则，未匹配或解析失败时使用正则清理规则，
+            // 最后按配置转换简繁，并使文件名在目标文件系统上合法
+            let new_filename = match rename_source.as_deref() {
+                Some(source) if options_ref.enable_renaming => {
+                    let templated = if filepath.is_file() {
+                        matcher_ref.apply_rename_template(source)
+                    } else {
+                        None
+                    };
+                    let cleaned = match templated {
+                        Some(result) => {
+                            template_report_ref
+                                .lock()
+                                .unwrap()
+                                .push((filepath.to_path_buf(), result.clone()));
+                            result.unwrap_or_else(|_| matcher_ref.clean_filename(source))
+                        }
+                        None => matcher_ref.clean_filename(source),
+                    };
+                    matcher_ref.sanitize_filename(matcher_ref.convert_script(cleaned))
+                }
+                Some(source) => source.to_string(),
+                None => filename.to_string(),
+            };
+
+            // 检查是否匹配整理规则：按新文件名移动到模板指定的目录（根目录除外）
+            if options_ref.enable_organizing
+                && entry.depth() > 0
+                && rename_source.is_some()
+                && !new_filename.is_empty()
+            {
+                // 相对路径基于目标路径，绝对路径直接使用
+                if let Some((dest, root)) =
+                    matcher_ref.match_organize(&new_filename, &options_ref.target_path)
+                {
+                    // 渲染结果不能离开目标路径（或绝对模板中的媒体库根目录）
+                    let dest_path = util::normalize_path(&dest.join(&new_filename));
+                    let root = util::normalize_path(&root);
+                    if !dest_path.starts_with(&root) || dest_path == root {
+                        eprintln!(
+                            "{} 整理目标位于 {:?} 之外，不移动 {:?}: {:?}",
+                            "[警告]".yellow(),
+                            root,
+                            filepath,
+                            dest_path
+                        );
+                    } else if dest_path != filepath {
+                        return Some((
+                            filepath.to_path_buf(),
+                            (dest_path.to_string_lossy().into_owned(), data::Operation::Move),
+                        ));
+                    }
                 }
             }
 
+            // 检查是否需要重命名：目标路径自身只在 `--rename-root` 时重命名，且不移动其内容
+            if options_ref.enable_renaming
+                && new_filename != filename
+                && (entry.depth() > 0 || options_ref.rename_root)
+            {
+                // 检查是否是目录且清理结果为空（只保留路径部分，文件名为空）
+                if filepath.is_dir() {
+                    let cleaned_name = PathBuf::from(&new_filename)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_default();
+
+                    if cleaned_name.is_empty() {
+                        // 目标路径自身的内容不移出目标路径
+                        if entry.depth() == 0 {
+                            return Some((
+                                filepath.to_path_buf(),
+                                ("".to_string(), data::Operation::None),
+                            ));
+                        }
+                        // 目录名被完全清理，需要移动内容到父目录
+                        return Some((
+                            filepath.to_path_buf(),
+                            ("".to_string(), data::Operation::MoveToParent),
+                        ));
+                    }
+                }
+
+                return Some((
+                    filepath.to_path_buf(),
+                    (new_filename, data::Operation::Rename),
+                ));
+            }
+
             // 检查是否为空目录（但排除符号链接目录）
-            if options_ref.enable_prune_empty_dir && filepath.is_dir() && !filepath.is_symlink() {
-                if filepath
+            if options_ref.enable_prune_empty_dir
+                && entry.depth() > 0
+                && filepath.is_dir()
+                && !filepath.is_symlink()
+                && filepath
                     .read_dir()
                     .map(|mut d| d.next().is_none())
                     .unwrap_or(false)
-                {
-                    return Some((
-                        filepath.to_path_buf(),
-                        ("<EMPTY_DIR>".to_string(), data::Operation::Delete),
-                    ));
-                }
+            {
+                return Some((
+                    filepath.to_path_buf(),
+                    ("<EMPTY_DIR>".to_string(), data::Operation::Delete),
+                ));
             }
 
             // 不需要操作的文件
@@ -173,6 +534,40 @@ fn main() -> std::io::Result<()> {
         })
         .collect();
 
+    let identities: fsops::Identities = identities.into_inner().unwrap();
+    // 删除前确认条目仍是规划时的那一个（重命名和移动在执行时逐条确认）
+    let unchanged = |path: &Path| {
+        let unchanged = identities
+            .get(path)
+            .is_some_and(|identity| identity.matches(path));
+        if !unchanged {
+            eprintln!(
+                "{} 规划之后已被修改或替换，跳过: {:?}",
+                "[警告]".yellow(),
+                path
+            );
+        }
+        unchanged
+    };
+
+    // 报告模板重命名的解析结果
+    let mut template_report = template_report.into_inner().unwrap();
+    if !template_report.is_empty() {
+        template_report.sort();
+        println!("{} 重命名模板解析结果:", "[信息]".blue());
+        for (path, result) in template_report {
+            match result {
+                Ok(new_name) => println!("  {} {:#?} ==> {}", "[✓]".green(), path, new_name),
+                Err(e) => println!(
+                    "  {} {:#?} 解析失败，使用清理规则: {}",
+                    "[✗]".red(),
+                    path,
+                    e
+                ),
+            }
+        }
+    }
+
     // 构建文件信息映射
     let mut file_info: HashMap<PathBuf, (String, data::Operation)> = HashMap::new();
     let mut all_paths: Vec<PathBuf> = Vec::with_capacity(file_info_results.len());
@@ -182,6 +577,15 @@ fn main() -> std::io::Result<()> {
         file_info.insert(path, info);
     }
 
+    // 字幕、NFO、海报等附属文件跟随媒体文件重命名和移动
+    let mut sidecar_followers: HashMap<PathBuf, (PathBuf, String)> = HashMap::new();
+    if app_options.enable_renaming || app_options.enable_organizing {
+        if let Some(sidecar_config) = &pattern_matcher.sidecar {
+            let sidecars = sidecar::find_sidecars(&all_paths, sidecar_config);
+            sidecar_followers = sidecar::follow_media(&mut file_info, &sidecars);
+        }
+    }
+
     // 构建操作列表
     let operation_list: Vec<(PathBuf, String, data::Operation)> = file_info
         .iter()
@@ -217,15 +621,19 @@ fn main() -> std::io::Result<()> {
             // 目录子项映射
             let mut dir_children: HashMap<&PathBuf, Vec<&PathBuf>> = HashMap::new();
 
-            let dirs: Vec<&PathBuf> = all_paths.iter().filter(|p| p.is_dir() && !p.is_symlink()).collect();
+            // 目标路径自身不作为空目录删除
+            let dirs: Vec<&PathBuf> = all_paths
+                .iter()
+                .filter(|p| **p != app_options.target_path && p.is_dir() && !p.is_symlink())
+                .collect();
 
             // 初始化目录映射
             for &dir in &dirs {
                 dir_children.insert(dir, Vec::new());
             }
 
-            // 构建父子关系
-            for path in all_paths.iter() {
+            // 构建父子关系；跳过的条目没有扫描，但仍在目录中，所在目录不是空目录
+            for path in all_paths.iter().chain(skipped.iter()) {
                 if let Some(parent) = path.parent().map(PathBuf::from) {
                     if let Some(actual_parent) = paths_set.get(&parent) {
                         if !to_delete.contains(path) {
@@ -244,7 +652,7 @@ fn main() -> std::io::Result<()> {
 
                 for &dir in dirs.iter() {
                     if !to_delete.contains(&dir)
-                        && dir_children.get(dir).map_or(true, |c| c.is_empty())
+                        && dir_children.get(dir).is_none_or(|c| c.is_empty())
                     {
                         empty_dirs.push(dir);
                     }
@@ -274,6 +682,9 @@ fn main() -> std::io::Result<()> {
 
         // 第二阶段：更新 file_info
         for dir in dirs_to_mark_delete {
+            if is_in_progress(&dir) {
+                continue;
+            }
             file_info.insert(dir, ("<EMPTY_DIR>".to_string(), data::Operation::Delete));
         }
     }
@@ -281,6 +692,46 @@ fn main() -> std::io::Result<()> {
     // 完成进度条
     process_bar.finish_with_message("文件处理完成");
 
+    // 删除、移动或展开上级目录会连同受保护的目录树一起删除或移动
+    for (path, (pattern, op)) in file_info.iter_mut() {
+        let action = match op {
+            data::Operation::Delete => "删除",
+            data::Operation::MoveToParent | data::Operation::Move => "移动",
+            _ => continue,
+        };
+        if protected.iter().any(|dir| dir.starts_with(path)) {
+            println!(
+                "{} {:#?} 包含受 {} 保护的目录，不{}",
+                "[提示]".blue(),
+                path,
+                NO_CLEANUP_MARKER,
+                action
+            );
+            pattern.clear();
+            *op = data::Operation::None;
+        }
+    }
+
+    // 与目标路径之外（如做种目录）共享硬链接的文件，删除后不会释放空间，可以保留
+    if app_options.keep_shared_links {
+        let shared: Vec<&Path> = hard_links(paths.iter().map(PathBuf::as_path))
+            .into_values()
+            .filter(|(nlink, links)| *nlink > links.len() as u64)
+            .flat_map(|(_, links)| links)
+            .collect();
+        for (path, (pattern, op)) in file_info.iter_mut() {
+            if *op == data::Operation::Delete && shared.iter().any(|file| file.starts_with(path)) {
+                println!(
+                    "{} {:#?} 与目标路径之外的文件共享硬链接，不删除",
+                    "[提示]".blue(),
+                    path
+                );
+                pattern.clear();
+                *op = data::Operation::None;
+            }
+        }
+    }
+
     // 首先，直接复制所有操作到 effective_operations，不做修改
     let mut effective_operations: HashMap<PathBuf, (String, data::Operation)> = file_info.clone();
 
@@ -333,6 +784,75 @@ fn main() -> std::io::Result<()> {
         effective_operations.insert(path, (new_pattern, data::Operation::Delete));
     }
 
+    // 校验失败的种子内容保持原样
+    for (path, (pattern, op)) in effective_operations.iter_mut() {
+        if blocked_roots.iter().any(|root| path.starts_with(root)) {
+            pattern.clear();
+            *op = data::Operation::None;
+        }
+    }
+
+    // 删除后仍有其他硬链接的文件不会释放空间
+    if app_options.enable_deletion {
+        let deleted = effective_operations
+            .iter()
+            .filter(|(_, (_, op))| *op == data::Operation::Delete)
+            .map(|(path, _)| path.as_path());
+        let mut remaining: Vec<(&Path, u64)> = hard_links(deleted)
+            .into_values()
+            .filter(|(nlink, links)| *nlink > links.len() as u64)
+            .flat_map(|(nlink, links)| {
+                let count = nlink - links.len() as u64;
+                links.into_iter().map(move |path| (path, count))
+            })
+            .collect();
+        remaining.sort();
+        if !remaining.is_empty() {
+            println!("{} 以下文件还有其他硬链接，删除后不会释放空间:", "[提示]".blue());
+            for (path, count) in remaining {
+                println!("  {:#?} （其他硬链接 {} 个）", path, count);
+            }
+        }
+    }
+
+    // 删除计划超出安全限制时中止执行（如 `remove` 中误写了 `*`）
+    if app_options.enable_deletion {
+        let total_files = entries.iter().filter(|e| !e.file_type().is_dir()).count() as u64;
+        let summary = limits::summarize(
+            &effective_operations,
+            total_files,
+            &pattern_matcher.limits,
+            |name| pattern_matcher.match_allow_large(name),
+        );
+        let violations = limits::check(&summary, &pattern_matcher.limits);
+        if !violations.is_empty() {
+            eprintln!(
+                "{} 删除计划超出安全限制（共删除 {} 个文件，{}）:",
+                "[警告]".yellow(),
+                summary.files,
+                indicatif::HumanBytes(summary.bytes)
+            );
+            for violation in &violations {
+                eprintln!("  {}", violation);
+            }
+            if !app_options.prune {
+                eprintln!("{} 使用 --prune 执行时将中止，除非同时使用 --force", "[提示]".blue());
+            } else if app_options.force {
+                eprintln!("{} 已使用 --force，继续执行", "[警告]".yellow());
+            } else {
+                eprintln!("{} 已中止，没有执行任何操作；使用 --force 忽略安全限制", "[错误]".red());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // 执行前记录受影响目录的时间，全部操作完成后恢复
+    let dir_times = if app_options.prune && app_options.preserve_dir_times {
+        executor::record_dir_times(&effective_operations, &app_options)
+    } else {
+        Vec::new()
+    };
+
     // 执行删除操作
     if app_options.enable_deletion {
         // 收集所有删除操作，区分直接删除和因父目录删除而受影响的项目
@@ -349,7 +869,8 @@ fn main() -> std::io::Result<()> {
             }
         }
 
-        // 执行直接删除操作
+        // 执行直接删除操作：深层条目先删除，空目录最后按非递归方式删除
+        direct_deletes.sort_by_key(|(path, _)| std::cmp::Reverse(path.components().count()));
         for (file_path, pattern) in direct_deletes {
             if app_options.verbose > 0 {
                 println!("{} {:#?} <== {}", "[-]".red(), file_path, pattern);
@@ -358,9 +879,21 @@ fn main() -> std::io::Result<()> {
             }
 
             if app_options.prune {
-                match util::remove_path(file_path.clone()) {
+                if util::path_exists(file_path) && !unchanged(file_path) {
+                    continue;
+                }
+                // 规划时为空的目录中可能出现了新条目，不递归删除
+                let result = if pattern == "<EMPTY_DIR>" {
+                    std::fs::remove_dir(file_path)
+                } else {
+                    util::remove_path(file_path.clone())
+                };
+                match result {
                     Ok(_) => (),
                     Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                    Err(e) if e.kind() == std::io::ErrorKind::DirectoryNotEmpty => {
+                        eprintln!("{} 目录已不再为空，跳过: {:?}", "[警告]".yellow(), file_path)
+                    }
                     Err(e) => {
                         eprintln!("{} 删除文件失败 {:?}: {}", "[错误]".red(), file_path, e)
                     }
@@ -383,9 +916,10 @@ fn main() -> std::io::Result<()> {
         }
     }
 
-    // 首先处理移动到父目录的操作
-    if app_options.enable_renaming {
-        let move_to_parent_operations: Vec<PathBuf> = effective_operations
+    // 首先处理移动到父目录的操作，记录移动过的路径，供后续重命名使用新位置
+    let mut moved_paths = executor::MoveLog::default();
+    if app_options.enable_renaming || flatten_rule.is_some() {
+        let mut move_to_parent_operations: Vec<PathBuf> = effective_operations
             .iter()
             .filter(|(_, (_, op))| *op == data::Operation::MoveToParent)
             .filter(|(_path, (pattern, _))| {
@@ -395,204 +929,313 @@ fn main() -> std::io::Result<()> {
             .map(|(original_path, _)| original_path.clone())
             .collect();
 
-        for dir_path in move_to_parent_operations {
-            println!("{} {:#?} ==> 移动内容到父目录", "[*]".yellow(), dir_path);
-
-            if let Some(parent_dir) = dir_path.parent() {
-                if app_options.prune {
-                    // 移动目录中的所有内容到父目录
-                    if let Ok(entries) = std::fs::read_dir(&dir_path) {
-                        for entry in entries {
-                            if let Ok(entry) = entry {
-                                let source_path = entry.path();
-                                let filename = entry.file_name();
-                                let mut target_path = parent_dir.join(&filename);
-
-                                // 处理命名冲突
-                                if target_path.exists() {
-                                    let original_name = filename.to_string_lossy();
-                                    let (name_without_ext, extension) =
-                                        if let Some(dot_pos) = original_name.rfind('.') {
-                                            let name_part = &original_name[..dot_pos];
-                                            let ext_part = &original_name[dot_pos..];
-                                            (name_part, ext_part)
-                                        } else {
-                                            (original_name.as_ref(), "")
-                                        };
-
-                                    let mut counter = 1;
-                                    loop {
-                                        let new_name = format!(
-                                            "{}({}){}",
-                                            name_without_ext, counter, extension
-                                        );
-                                        target_path = parent_dir.join(&new_name);
-
-                                        if !target_path.exists() {
-                                            println!(
-                                                "  {} 目标已存在，使用新名称: {}",
-                                                "[提示]".blue(),
-                                                new_name
-                                            );
-                                            break;
-                                        }
-
-                                        counter += 1;
-                                        if counter > 999 {
-                                            eprintln!(
-                                                "{} 无法找到可用的移动目标（尝试了999个后缀）: {:?}",
-                                                "[错误]".red(),
-                                                source_path
-                                            );
-                                            break;
-                                        }
-                                    }
-                                }
-
-                                println!(
-                                    "  --> 移动 {} 到 {}",
-                                    source_path.display().to_string().cyan(),
-                                    target_path.display().to_string().cyan()
-                                );
-                                match std::fs::rename(&source_path, &target_path) {
-                                    Ok(_) => (),
-                                    Err(e) => {
-                                        eprintln!(
-                                            "{} 移动文件失败 {:?} -> {:?}: {}",
-                                            "[错误]".red(),
-                                            source_path,
-                                            target_path,
-                                            e
-                                        );
-                                    }
-                                }
-                            }
-                        }
+        // 深层目录先处理，嵌套的包装目录逐层展开
+        move_to_parent_operations.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
 
-                        // 移动完成后删除空目录
-                        match std::fs::remove_dir(&dir_path) {
-                            Ok(_) => println!(
-                                "  --> 删除空目录 {}",
-                                dir_path.display().to_string().cyan()
-                            ),
-                            Err(e) => {
-                                eprintln!(
-                                    "{} 删除空目录失败 {:?}: {}",
-                                    "[错误]".red(),
-                                    dir_path,
-                                    e
-                                );
-                            }
-                        }
-                    } else {
-                        eprintln!("{} 无法读取目录内容: {:?}", "[错误]".red(), dir_path);
-                    }
-                } else {
-                    println!(
-                        "  --> 预览：将移动目录内容到 {}",
-                        parent_dir.display().to_string().cyan()
-                    );
-                }
-            } else {
-                eprintln!("{} 无法获取父目录: {:?}", "[错误]".red(), dir_path);
-            }
+        for original in move_to_parent_operations {
+            let dir_path = util::remap_path(&original, &moved_paths);
+            executor::execute_move_to_parent(
+                &dir_path,
+                &app_options,
+                &mut moved_paths,
+                &identities,
+            );
+        }
+    }
+
+    // 执行整理（移动）操作
+    if app_options.enable_organizing {
+        let mut move_operations: Vec<(PathBuf, PathBuf)> = effective_operations
+            .iter()
+            .filter(|(_, (_, op))| *op == data::Operation::Move)
+            .filter(|(path, _)| !sidecar_followers.contains_key(*path))
+            .map(|(original_path, (dest, _))| (original_path.clone(), PathBuf::from(dest)))
+            .collect();
+        // 深层条目先处理，避免父目录先被移走
+        move_operations.sort_by(|(a, _), (b, _)| {
+            b.components().count().cmp(&a.components().count()).then(a.cmp(b))
+        });
+
+        for (original, dest) in move_operations {
+            let source = util::remap_path(&original, &moved_paths);
+            executor::execute_move(&source, &dest, &app_options, &mut moved_paths, &identities);
         }
     }
 
     // 执行重命名操作
     if app_options.enable_renaming {
-        let mut rename_operations: Vec<(PathBuf, String)> = effective_operations
+        let rename_operations: Vec<(PathBuf, String)> = effective_operations
             .iter()
             .filter(|(_, (_, op))| *op == data::Operation::Rename)
-            .filter(|(_path, (pattern, _))| {
-                // 过滤掉被标记为"父目录被删除"的项目
-                !pattern.starts_with("父目录被删除:")
+            .filter(|(path, (pattern, _))| {
+                // 过滤掉被标记为"父目录被删除"的项目，以及之后跟随媒体文件处理的附属文件
+                !pattern.starts_with("父目录被删除:") && !sidecar_followers.contains_key(*path)
             })
             .map(|(original_path, (new_file_name, _))| {
-                (original_path.clone(), new_file_name.clone())
+                (
+                    util::remap_path(original_path, &moved_paths),
+                    new_file_name.clone(),
+                )
             })
             .collect();
 
-        // 按深度排序：深度大的（子项）先处理，深度小的（父项）后处理
-        rename_operations.sort_by(|a, b| {
-            let depth_a = a.0.components().count();
-            let depth_b = b.0.components().count();
-            depth_b.cmp(&depth_a) // 从深到浅排序
-        });
-
-        'outer: for (original_path, new_file_name) in rename_operations {
-            println!(
-                "{} {:#?} ==> {}",
-                "[*]".yellow(),
-                original_path,
-                new_file_name
-            );
+        executor::execute_renames(
+            rename_operations,
+            &app_options,
+            &mut moved_paths,
+            &identities,
+        );
+    }
 
-            let mut final_filepath = original_path.clone();
-            final_filepath.set_file_name(&new_file_name);
+    // 附属文件在媒体文件的最终名称（可能带有冲突后缀）确定之后再重命名或移动，保持主名一致
+    if !sidecar_followers.is_empty() {
+        sidecar_followers.retain(|path, _| {
+            effective_operations.get(path).is_some_and(|(pattern, op)| {
+                matches!(op, data::Operation::Rename | data::Operation::Move)
+                    && !pattern.starts_with("父目录被删除:")
+            })
+        });
+        let mut rename_operations: Vec<(PathBuf, String)> = Vec::new();
+        for (original, target) in sidecar::resolve_targets(&sidecar_followers, &moved_paths) {
+            let current = util::remap_path(&original, &moved_paths);
+            if current == target {
+                continue;
+            }
+            if current.parent() == target.parent() {
+                let name = target.file_name().unwrap().to_string_lossy().into_owned();
+                rename_operations.push((current, name));
+            } else {
+                executor::execute_move(
+                    &current,
+                    &target,
+                    &app_options,
+                    &mut moved_paths,
+                    &identities,
+                );
+            }
+        }
+        executor::execute_renames(
+            rename_operations,
+            &app_options,
+            &mut moved_paths,
+            &identities,
+        );
+    }
 
-            // 处理重命名冲突：如果目标路径已存在，添加后缀 (1), (2), ...
-            if final_filepath.exists() {
-                let parent = original_path.parent().unwrap();
-                let original_name = &new_file_name;
+    // 保留的校验文件中，改写被重命名、移动的文件名
+    update_manifests(&manifests, &effective_operations, &moved_paths, &app_options);
 
-                // 分离文件名和扩展名
-                let (name_without_ext, extension) = if let Some(dot_pos) = original_name.rfind('.')
-                {
-                    let name_part = &original_name[..dot_pos];
-                    let ext_part = &original_name[dot_pos..];
-                    (name_part, ext_part)
-                } else {
-                    (original_name.as_str(), "")
-                };
+    executor::restore_dir_times(dir_times, &moved_paths);
 
-                let mut counter = 1;
-                loop {
-                    let new_name = format!("{}({}){}", name_without_ext, counter, extension);
-                    let test_path = parent.join(&new_name);
+    Ok(())
+}
 
-                    if !test_path.exists() {
-                        println!("  {} 目标已存在，使用新名称: {}", "[提示]".blue(), new_name);
-                        final_filepath = test_path;
-                        break;
-                    }
+/**
+ * 按 inode 对有多个硬链接的文件分组，返回 (设备号, inode) => (硬链接总数, 其中的路径)
+ */
+fn hard_links<'a>(
+    paths: impl Iterator<Item = &'a Path>,
+) -> HashMap<(u64, u64), (u64, Vec<&'a Path>)> {
+    let mut groups: HashMap<(u64, u64), (u64, Vec<&Path>)> = HashMap::new();
+    for path in paths {
+        let Ok(metadata) = path.symlink_metadata() else {
+            continue;
+        };
+        if metadata.is_file() && metadata.nlink() > 1 {
+            groups
+                .entry((metadata.dev(), metadata.ino()))
+                .or_insert_with(|| (metadata.nlink(), Vec::new()))
+                .1
+                .push(path);
+        }
+    }
+    groups
+}
 
-                    counter += 1;
-                    if counter > 999 {
-                        eprintln!(
-                            "{} 无法找到可用的重命名目标（尝试了999个后缀）: {:?}",
-                            "[错误]".red(),
-                            original_path
-                        );
-                        continue 'outer;
+/**
+ * 校验目标路径中的种子内容，报告损坏的文件。返回需要跳过清理的内容根目录（`--verify-strict`）
+ */
+fn verify_torrents(
+    torrents: &[(PathBuf, torrent::Torrent)],
+    app_options: &data::AppOptions,
+) -> Vec<PathBuf> {
+    // 只校验位于目标路径之中的内容
+    let targets: Vec<_> = torrents
+        .iter()
+        .filter_map(|(torrent_path, parsed)| {
+            match parsed.content_root(torrent_path, &app_options.target_path) {
+                Some(root) if root.starts_with(&app_options.target_path) => Some((parsed, root)),
+                Some(_) => None,
+                None => {
+                    if torrent_path.starts_with(&app_options.target_path) {
+                        eprintln!("{} 找不到种子内容，无法校验: {:?}", "[警告]".yellow(), torrent_path);
                     }
+                    None
                 }
             }
+        })
+        .collect();
+    if targets.is_empty() {
+        return Vec::new();
+    }
 
-            if app_options.prune {
-                println!("--> {}", final_filepath.display().to_string().cyan());
-                match rename(&original_path, &final_filepath) {
-                    Ok(_) => (),
-                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                        eprintln!(
-                            "{} 源文件不存在，可能已被父目录操作影响: {:?}",
-                            "[警告]".yellow(),
-                            original_path
-                        );
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "{} 重命名文件失败 {:?} -> {:?}: {}",
-                            "[错误]".red(),
-                            original_path,
-                            final_filepath,
-                            e
-                        );
-                    }
-                }
+    println!("正在校验种子内容...");
+    let total: usize = targets.iter().map(|(parsed, _)| parsed.pieces.len()).sum();
+    let verify_bar = util::progress_bar(total as u64);
+    let results: Vec<_> = targets
+        .into_iter()
+        .map(|(parsed, root)| {
+            verify_bar.set_message(format!("校验: {}", root.display()));
+            let result = torrent::verify(parsed, &root, &verify_bar);
+            (root, result)
+        })
+        .collect();
+    verify_bar.finish_with_message("校验完成");
+
+    let mut blocked = Vec::new();
+    for (root, result) in results {
+        if result.failed_pieces == 0 {
+            println!(
+                "{} 校验通过: {:?} ({} 个分块)",
+                "[✓]".green(),
+                root,
+                result.total_pieces
+            );
+            continue;
+        }
+        println!(
+            "{} 校验失败: {:?} ({}/{} 个分块损坏)",
+            "[✗]".red(),
+            root,
+            result.failed_pieces,
+            result.total_pieces
+        );
+        for path in &result.damaged_files {
+            println!("  {} {:#?}", "[✗]".red(), path);
+        }
+        if app_options.verify_strict {
+            eprintln!("{} 校验失败，不清理: {:?}", "[警告]".yellow(), root);
+            blocked.push(root);
+        }
+    }
+    blocked
+}
+
+/**
+ * 解析并校验目标路径中的校验文件，报告缺失和不一致的文件。返回 (校验文件, 是否全部通过)
+ */
+fn verify_manifests(paths: &[PathBuf]) -> Vec<(manifest::Manifest, bool)> {
+    let manifests: Vec<manifest::Manifest> = paths
+        .iter()
+        .filter(|path| manifest::Algorithm::from_path(path).is_some() && path.is_file())
+        .filter_map(|path| match manifest::Manifest::from_file(path) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                eprintln!("{} 无法解析校验文件 {:?}: {}", "[警告]".yellow(), path, e);
+                None
+            }
+        })
+        .collect();
+    if manifests.is_empty() {
+        return Vec::new();
+    }
+
+    println!("正在校验校验文件...");
+    let records: Vec<(usize, usize)> = manifests
+        .iter()
+        .enumerate()
+        .flat_map(|(i, parsed)| (0..parsed.entries.len()).map(move |j| (i, j)))
+        .collect();
+    let verify_bar = util::progress_bar(records.len() as u64);
+    let statuses: Vec<manifest::Status> = records
+        .par_iter()
+        .map(|&(i, j)| {
+            let entry = &manifests[i].entries[j];
+            verify_bar.set_message(format!("校验: {}", entry.name));
+            let status = manifests[i].check(entry);
+            verify_bar.inc(1);
+            status
+        })
+        .collect();
+    verify_bar.finish_with_message("校验完成");
+
+    let mut results = Vec::with_capacity(manifests.len());
+    let mut statuses = statuses.into_iter();
+    for parsed in manifests {
+        let failures: Vec<_> = parsed
+            .entries
+            .iter()
+            .zip(statuses.by_ref())
+            .filter(|(_, status)| *status != manifest::Status::Ok)
+            .collect();
+        if failures.is_empty() {
+            println!(
+                "{} 校验通过: {:?} ({} 个文件)",
+                "[✓]".green(),
+                parsed.path,
+                parsed.entries.len()
+            );
+        } else {
+            println!(
+                "{} 校验失败: {:?} ({}/{} 个文件)",
+                "[✗]".red(),
+                parsed.path,
+                failures.len(),
+                parsed.entries.len()
+            );
+            for (entry, status) in &failures {
+                let reason = match status {
+                    manifest::Status::Missing => "缺失",
+                    _ => "不一致",
+                };
+                println!("  {} {} ({})", "[✗]".red(), entry.name, reason);
             }
         }
+        let passed = failures.is_empty();
+        results.push((parsed, passed));
     }
+    results
+}
 
-    Ok(())
+/**
+ * 改写保留的校验文件中被重命名、移动的文件名（预览模式下按预计的移动记录显示）
+ */
+fn update_manifests(
+    manifests: &[(manifest::Manifest, bool)],
+    operations: &HashMap<PathBuf, (String, data::Operation)>,
+    moved_paths: &[(PathBuf, PathBuf)],
+    app_options: &data::AppOptions,
+) {
+    for (parsed, _) in manifests {
+        // 被删除的校验文件不需要改写
+        if operations
+            .get(&parsed.path)
+            .is_some_and(|(_, op)| *op == data::Operation::Delete)
+        {
+            continue;
+        }
+        let renames = parsed.plan_renames(moved_paths);
+        if renames.is_empty() {
+            continue;
+        }
+        let manifest_path = util::remap_path(&parsed.path, moved_paths);
+        println!(
+            "{} {:#?} ==> 更新校验文件中的文件名",
+            "[*]".yellow(),
+            manifest_path
+        );
+        for (index, new_name) in &renames {
+            println!("  {} --> {}", parsed.entries[*index].name, new_name.cyan());
+        }
+        if app_options.prune {
+            if let Err(e) = std::fs::write(&manifest_path, parsed.render(&renames)) {
+                eprintln!(
+                    "{} 更新校验文件失败 {:?}: {}",
+                    "[错误]".red(),
+                    manifest_path,
+                    e
+                );
+            }
+        }
+    }
 }
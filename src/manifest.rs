@@ -0,0 +1,245 @@
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::pmatcher;
+use crate::util;
+
+/// 校验文件使用的算法，由扩展名决定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    /// `.sfv`：`文件名 CRC32`
+    Crc32,
+    /// `.md5`：`md5sum` 或 BSD 格式
+    Md5,
+    /// `.sha256`：`sha256sum` 或 BSD 格式
+    Sha256,
+}
+
+impl Algorithm {
+    pub fn from_path(path: &Path) -> Option<Algorithm> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "sfv" => Some(Algorithm::Crc32),
+            "md5" => Some(Algorithm::Md5),
+            "sha256" => Some(Algorithm::Sha256),
+            _ => None,
+        }
+    }
+
+    /// 十六进制摘要的长度
+    fn hex_len(&self) -> usize {
+        match self {
+            Algorithm::Crc32 => 8,
+            Algorithm::Md5 => 32,
+            Algorithm::Sha256 => 64,
+        }
+    }
+
+    /// BSD 格式中的算法标记，如 `MD5 (file) = ...`
+    fn bsd_tag(&self) -> &'static str {
+        match self {
+            Algorithm::Crc32 => "CRC32",
+            Algorithm::Md5 => "MD5",
+            Algorithm::Sha256 => "SHA256",
+        }
+    }
+}
+
+/// 校验文件中的一条记录
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// 文件名（相对于校验文件所在目录，`\` 已转为 `/`）
+    pub name: String,
+    /// 小写的十六进制摘要
+    pub hash: String,
+    /// 所在行号（从 0 开始），以及文件名在该行中的字节范围，用于改写文件名
+    line: usize,
+    span: (usize, usize),
+}
+
+/**
+ * 解析后的校验文件（.sfv / .md5 / .sha256）
+ */
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    pub path: PathBuf,
+    pub algorithm: Algorithm,
+    pub entries: Vec<Entry>,
+    lines: Vec<String>,
+    /// 开头的 UTF-8 BOM，改写时保留
+    bom: bool,
+}
+
+/// 一条记录的校验结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Ok,
+    Missing,
+    Mismatch,
+}
+
+impl Manifest {
+    pub fn from_file(path: &Path) -> Result<Manifest, String> {
+        let algorithm = Algorithm::from_path(path).ok_or("不支持的校验文件类型")?;
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let text = String::from_utf8(data).map_err(|_| "不是有效的 UTF-8".to_string())?;
+        let bom = text.starts_with('\u{feff}');
+        let text = text.strip_prefix('\u{feff}').unwrap_or(&text);
+        let lines: Vec<String> = text.split('\n').map(String::from).collect();
+        let mut entries = Vec::new();
+        for (index, line) in lines.iter().enumerate() {
+            let content = line.trim_end_matches('\r');
+            let parsed = match algorithm {
+                Algorithm::Crc32 => parse_sfv_line(content),
+                _ => parse_sum_line(content, algorithm),
+            };
+            if let Some((hash, span)) = parsed {
+                entries.push(Entry {
+                    name: content[span.0..span.1].replace('\\', "/"),
+                    hash: hash.to_ascii_lowercase(),
+                    line: index,
+                    span,
+                });
+            }
+        }
+        if entries.is_empty() {
+            return Err("没有有效的记录".to_string());
+        }
+        Ok(Manifest {
+            path: path.to_path_buf(),
+            algorithm,
+            entries,
+            lines,
+            bom,
+        })
+    }
+
+    /// 记录对应的文件路径
+    pub fn entry_path(&self, entry: &Entry) -> PathBuf {
+        self.path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&entry.name)
+    }
+
+    /**
+     * 校验一条记录：文件不存在为 Missing，摘要不一致（或无法读取）为 Mismatch
+     */
+    pub fn check(&self, entry: &Entry) -> Status {
+        let path = self.entry_path(entry);
+        if !path.is_file() {
+            return Status::Missing;
+        }
+        match hash_file(&path, self.algorithm) {
+            Ok(hash) if hash == entry.hash => Status::Ok,
+            _ => Status::Mismatch,
+        }
+    }
+
+    /**
+     * 将记录中的文件名改为新名称（entry 序号 => 相对于校验文件所在目录的新名称），
+     * 返回改写后的内容；文件名之外的部分（摘要、注释、换行符）保持不变
+     */
+    pub fn render(&self, renames: &[(usize, String)]) -> String {
+        let mut lines = self.lines.clone();
+        for (index, new_name) in renames {
+            let entry = &self.entries[*index];
+            let line = &self.lines[entry.line];
+            lines[entry.line] = format!(
+                "{}{}{}",
+                &line[..entry.span.0],
+                new_name,
+                &line[entry.span.1..]
+            );
+        }
+        let content = lines.join("\n");
+        if self.bom {
+            format!("\u{feff}{}", content)
+        } else {
+            content
+        }
+    }
+
+    /**
+     * 按移动记录计算需要改写的文件名：记录对应的文件和校验文件自身都可能被重命名或移动，
+     * 新名称相对于校验文件的新位置。文件移出校验文件所在目录时无法表示，保持原样
+     */
+    pub fn plan_renames(&self, moves: &[(PathBuf, PathBuf)]) -> Vec<(usize, String)> {
+        let new_path = util::remap_path(&self.path, moves);
+        let Some(new_dir) = new_path.parent() else {
+            return Vec::new();
+        };
+        let mut renames = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let moved = util::remap_path(&self.entry_path(entry), moves);
+            let Some(name) = moved.strip_prefix(new_dir).ok().and_then(Path::to_str) else {
+                continue;
+            };
+            if !name.is_empty() && name != entry.name {
+                renames.push((index, name.to_string()));
+            }
+        }
+        renames
+    }
+}
+
+/**
+ * 按算法计算文件摘要，返回小写的十六进制字符串
+ */
+pub fn hash_file(path: &Path, algorithm: Algorithm) -> io::Result<String> {
+    match algorithm {
+        Algorithm::Crc32 => {
+            let mut hasher = crc32fast::Hasher::new();
+            pmatcher::read_chunks(path, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:08x}", hasher.finalize()))
+        }
+        Algorithm::Md5 => pmatcher::calculate_md5(path),
+        Algorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+            pmatcher::read_chunks(path, |chunk| hasher.update(chunk))?;
+            Ok(format!("{:x}", hasher.finalize()))
+        }
+    }
+}
+
+/// SFV：`文件名 CRC32`，文件名可以包含空格，`;` 开头的行是注释
+fn parse_sfv_line(line: &str) -> Option<(&str, (usize, usize))> {
+    if line.trim_start().starts_with(';') {
+        return None;
+    }
+    let trimmed = line.trim_end();
+    let (name, hash) = trimmed.rsplit_once([' ', '\t'])?;
+    let name_start = name.len() - name.trim_start().len();
+    let name_end = name.trim_end().len();
+    (is_hex(hash, Algorithm::Crc32.hex_len()) && name_start < name_end)
+        .then_some((hash, (name_start, name_end)))
+}
+
+/// `md5sum` 格式 `摘要  文件名`（二进制模式为 `摘要 *文件名`），或 BSD 格式 `MD5 (文件名) = 摘要`
+fn parse_sum_line(line: &str, algorithm: Algorithm) -> Option<(&str, (usize, usize))> {
+    if line.starts_with('#') {
+        return None;
+    }
+    let hex_len = algorithm.hex_len();
+    if let Some(rest) = line
+        .strip_prefix(algorithm.bsd_tag())
+        .and_then(|rest| rest.strip_prefix(" ("))
+    {
+        let (name, hash) = rest.rsplit_once(") = ")?;
+        let name_start = line.len() - rest.len();
+        return (is_hex(hash.trim_end(), hex_len) && !name.is_empty())
+            .then_some((hash.trim_end(), (name_start, name_start + name.len())));
+    }
+    let hash = line.get(..hex_len)?;
+    let marker = line.get(hex_len..hex_len + 2)?;
+    let name_start = hex_len + 2;
+    (is_hex(hash, hex_len) && (marker == "  " || marker == " *") && name_start < line.len())
+        .then_some((hash, (name_start, line.len())))
+}
+
+fn is_hex(text: &str, len: usize) -> bool {
+    text.len() == len && text.bytes().all(|b| b.is_ascii_hexdigit())
+}
+//EOP
@@ -6,14 +6,17 @@ use colored::*;
 use nary_tree::{NodeId, Tree, TreeBuilder};
 
 use crate::data::Operation;
+use crate::util;
 
 const SYMBOL_DIR: &str = "📁";
 const SYMBOL_FILE: &str = "📄";
 const SYMBOL_LINK: &str = "🔗";
+const SYMBOL_SPECIAL: &str = "🔌";
 const SYMBOL_BROKEN_ARROW: &str = "!>"; // ↛ ⥇ ⓧ ⊗ ⊘ ⤍ ⤑
 const SYMBOL_LINK_ARROW: &str = "->";
 const SYMBOL_DELETE: &str = "[-]"; // ␡
 const SYMBOL_RENAME: &str = "[*]"; //
+const SYMBOL_MOVE: &str = "[>]";
 
 pub fn path_list_to_tree(
     path_list: &Vec<(PathBuf, String, Operation)>,
@@ -69,7 +72,14 @@ pub fn path_list_to_tree(
                 } else if full_path.is_dir() {
                     (SYMBOL_DIR, component_str + "/")
                 } else {
-                    ("??", component_str)
+                    match full_path
+                        .symlink_metadata()
+                        .ok()
+                        .and_then(|m| util::special_file_kind(&m.file_type()))
+                    {
+                        Some(kind) => (SYMBOL_SPECIAL, format!("{} <{}>", component_str, kind)),
+                        None => ("??", component_str),
+                    }
                 };
 
                 let mut parent = tree.get_mut(current_node_id).unwrap();
@@ -92,10 +102,18 @@ pub fn path_list_to_tree(
                 let node_data = _node.data();
                 *node_data = format!("{} {} => {}", node_data, SYMBOL_RENAME.yellow(), _pattern);
             }
+            Operation::MoveToParent if _pattern == "<FLATTEN>" => {
+                let node_data = _node.data();
+                *node_data = format!("{} {} ↗ 展开冗余目录", node_data, SYMBOL_RENAME.green());
+            }
             Operation::MoveToParent => {
                 let node_data = _node.data();
                 *node_data = format!("{} {} ↗ 移动内容到父目录", node_data, SYMBOL_RENAME.green());
             }
+            Operation::Move => {
+                let node_data = _node.data();
+                *node_data = format!("{} {} => {}", node_data, SYMBOL_MOVE.cyan(), _pattern);
+            }
             _ => {}
         }
     }
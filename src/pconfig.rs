@@ -2,11 +2,235 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::path::Path;
 
+use crate::download;
+use crate::encoding;
+use crate::limits;
+use crate::sidecar;
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 pub struct PatternsConfig {
     pub remove: Vec<String>,
     pub remove_hash: HashMap<String, Vec<String>>,
     pub cleanup: Vec<String>,
+    pub skip: Vec<String>,
+    pub flatten: Option<FlattenConfig>,
+    pub sidecar: Option<SidecarConfig>,
+    pub sanitize: Option<SanitizeConfig>,
+    /// 按内容删除的范围：`true` 为目标路径下的所有文件（即 `*`），也可以是文件名或相对路径的规则
+    pub remove_empty_files: Vec<String>,
+    pub remove_zero_filled: Vec<String>,
+    pub manifest: ManifestConfig,
+    pub in_progress: Option<InProgressConfig>,
+    pub limits: LimitsConfig,
+    pub organize: Vec<OrganizeRule>,
+    pub rename_template: Vec<RenameTemplateRule>,
+    pub transcode: Option<Vec<String>>,
+    pub repair_encoding: Option<Vec<String>>,
+    pub zh_convert: Option<ZhConvertConfig>,
+}
+
+/// 整理（移动）规则：文件名匹配正则表达式（支持命名捕获组）时，移动到模板指定的目录
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct OrganizeRule {
+    pub pattern: String,
+    /// 目标目录模板，如 `{show}/Season {season:02}/`；相对路径基于目标路径，也可以是绝对路径
+    pub dest: String,
+}
+
+/// 模板重命名规则：文件名匹配正则表达式时，用发布名解析出的字段渲染模板作为新文件名
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RenameTemplateRule {
+    pub pattern: String,
+    /// 新文件名模板，如 `{title} - S{season:02}E{episode:02}.{ext}`
+    pub template: String,
+}
+
+/// 简繁转换
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ZhConvertConfig {
+    /// 匹配 `remove`、`remove_hash` 和 `cleanup` 规则时，规则和文件名都统一转为简体
+    #[serde(default)]
+    pub normalize: bool,
+    /// 将重命名后的文件名转为简体（hans）或繁体（hant）
+    #[serde(default)]
+    pub convert: Option<String>,
+}
+
+/// 附属文件（字幕、NFO、海报等）跟随媒体文件重命名的规则
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SidecarConfig {
+    /// 媒体文件的扩展名
+    #[serde(default = "default_media_extensions")]
+    pub media: Vec<String>,
+    /// 附属文件的扩展名
+    #[serde(default = "default_sidecar_extensions")]
+    pub extensions: Vec<String>,
+}
+
+impl Default for SidecarConfig {
+    fn default() -> Self {
+        SidecarConfig {
+            media: default_media_extensions(),
+            extensions: default_sidecar_extensions(),
+        }
+    }
+}
+
+fn default_media_extensions() -> Vec<String> {
+    sidecar::DEFAULT_MEDIA_EXTENSIONS.map(String::from).to_vec()
+}
+
+fn default_sidecar_extensions() -> Vec<String> {
+    sidecar::DEFAULT_SIDECAR_EXTENSIONS
+        .map(String::from)
+        .to_vec()
+}
+
+/// 文件名合法化：使重命名后的文件名在目标文件系统上合法
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct SanitizeConfig {
+    /// 目标文件系统：posix、windows、exfat
+    #[serde(default = "default_sanitize_profile")]
+    pub profile: String,
+    /// 非法字符的替换字符串，为空时删除
+    #[serde(default = "default_sanitize_replacement")]
+    pub replacement: String,
+}
+
+impl Default for SanitizeConfig {
+    fn default() -> Self {
+        SanitizeConfig {
+            profile: default_sanitize_profile(),
+            replacement: default_sanitize_replacement(),
+        }
+    }
+}
+
+fn default_sanitize_profile() -> String {
+    "posix".to_string()
+}
+
+fn default_sanitize_replacement() -> String {
+    "_".to_string()
+}
+
+/// 跳过仍在下载中的条目
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct InProgressConfig {
+    /// 下载中标记文件的扩展名
+    #[serde(default = "default_marker_extensions")]
+    pub markers: Vec<String>,
+    /// 在这个时间窗口（秒）内修改过的文件视为仍在下载，0 表示不检查
+    #[serde(default)]
+    pub recent_seconds: u64,
+}
+
+impl Default for InProgressConfig {
+    fn default() -> Self {
+        InProgressConfig {
+            markers: default_marker_extensions(),
+            recent_seconds: 0,
+        }
+    }
+}
+
+fn default_marker_extensions() -> Vec<String> {
+    download::DEFAULT_MARKER_EXTENSIONS
+        .map(String::from)
+        .to_vec()
+}
+
+/// 删除计划的安全限制，超出时中止执行（`--force` 忽略）
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct LimitsConfig {
+    /// 最多删除的文件数
+    #[serde(default)]
+    pub max_files: Option<u64>,
+    /// 最多删除的总大小，如 `10G`
+    #[serde(default, deserialize_with = "deserialize_size")]
+    pub max_bytes: Option<u64>,
+    /// 删除的文件最多占全部文件的百分比
+    #[serde(default = "default_max_percent")]
+    pub max_percent: Option<f64>,
+    /// 不删除大于此大小的文件，如 `4G`
+    #[serde(
+        default = "default_max_file_size",
+        deserialize_with = "deserialize_size"
+    )]
+    pub max_file_size: Option<u64>,
+    /// 允许删除大文件的规则（通配符或 `/` 开头的正则表达式）
+    #[serde(default)]
+    pub allow_large: Vec<String>,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_files: None,
+            max_bytes: None,
+            max_percent: default_max_percent(),
+            max_file_size: default_max_file_size(),
+            allow_large: vec![],
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// 不做任何限制（`limits: false`）
+    fn unlimited() -> Self {
+        LimitsConfig {
+            max_percent: None,
+            max_file_size: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// 默认不删除超过 90% 的文件，避免过宽的规则（如 `remove: *`）清空整个目标路径
+fn default_max_percent() -> Option<f64> {
+    Some(limits::DEFAULT_MAX_PERCENT)
+}
+
+fn default_max_file_size() -> Option<u64> {
+    Some(limits::DEFAULT_MAX_FILE_SIZE)
+}
+
+/// 大小：字节数，或带单位的字符串
+fn deserialize_size<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::Deserialize;
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::Null => Ok(None),
+        serde_yaml::Value::Number(n) => n
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("无效的大小: {}", n))),
+        serde_yaml::Value::String(s) => limits::parse_size(&s)
+            .map(Some)
+            .ok_or_else(|| serde::de::Error::custom(format!("无效的大小: {}", s))),
+        _ => Err(serde::de::Error::custom("无效的大小")),
+    }
+}
+
+/// 校验文件（.sfv / .md5 / .sha256）
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ManifestConfig {
+    /// 校验通过后仍然保留的校验文件（通配符或 `/` 开头的正则表达式），其余校验通过的会被删除
+    #[serde(default)]
+    pub keep: Vec<String>,
+}
+
+/// 展开冗余包装目录的规则
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct FlattenConfig {
+    /// 仅当唯一子目录与包装目录清理后的名称相同时才展开
+    #[serde(default)]
+    pub same_name: bool,
+    /// 目录中只有一个文件时也展开
+    #[serde(default)]
+    pub single_file: bool,
 }
 
 impl PatternsConfig {
@@ -17,6 +241,20 @@ impl PatternsConfig {
             remove: vec![],
             remove_hash: HashMap::new(),
             cleanup: vec![],
+            skip: vec![".tmp".to_string()],
+            flatten: None,
+            sidecar: None,
+            sanitize: Some(SanitizeConfig::default()),
+            remove_empty_files: vec![],
+            remove_zero_filled: vec![],
+            manifest: ManifestConfig::default(),
+            in_progress: Some(InProgressConfig::default()),
+            limits: LimitsConfig::default(),
+            organize: vec![],
+            rename_template: vec![],
+            transcode: None,
+            repair_encoding: None,
+            zh_convert: None,
         };
         for (key, value) in values {
             match key.as_str() {
@@ -62,10 +300,157 @@ impl PatternsConfig {
                     ),
                     _ => {}
                 },
+                // 配置后替换默认的 `.tmp`
+                "skip" => match value {
+                    serde_yaml::Value::String(s) => {
+                        config.skip = s
+                            .lines()
+                            .map(|v| v.trim().to_string())
+                            .filter(|v| !v.is_empty() && !v.starts_with('#'))
+                            .collect()
+                    }
+                    serde_yaml::Value::Sequence(s) => {
+                        config.skip = s
+                            .iter()
+                            .map(|v| v.as_str().unwrap().to_string())
+                            .collect()
+                    }
+                    _ => {}
+                },
+                "transcode" => {
+                    config.transcode = match value {
+                        serde_yaml::Value::Bool(false) => None,
+                        serde_yaml::Value::Bool(true) => Some(
+                            encoding::DEFAULT_TRANSCODE_ENCODINGS
+                                .map(String::from)
+                                .to_vec(),
+                        ),
+                        _ => parse_encoding_list(&value),
+                    }
+                }
+                "repair_encoding" => {
+                    config.repair_encoding = match value {
+                        serde_yaml::Value::Bool(false) => None,
+                        serde_yaml::Value::Bool(true) => Some(
+                            encoding::DEFAULT_REPAIR_ENCODINGS
+                                .map(String::from)
+                                .to_vec(),
+                        ),
+                        _ => parse_encoding_list(&value),
+                    }
+                }
+                "organize" => {
+                    if let serde_yaml::Value::Sequence(_) = value {
+                        config.organize = serde_yaml::from_value(value).unwrap();
+                    }
+                }
+                "rename_template" => {
+                    if let serde_yaml::Value::Sequence(_) = value {
+                        config.rename_template = serde_yaml::from_value(value).unwrap();
+                    }
+                }
+                "zh_convert" => {
+                    if let serde_yaml::Value::Mapping(_) = value {
+                        config.zh_convert = Some(serde_yaml::from_value(value).unwrap());
+                    }
+                }
+                "sidecar" => {
+                    config.sidecar = match value {
+                        serde_yaml::Value::Bool(false) => None,
+                        serde_yaml::Value::Mapping(_) => {
+                            Some(serde_yaml::from_value(value).unwrap())
+                        }
+                        _ => Some(SidecarConfig::default()),
+                    }
+                }
+                "remove_empty_files" => config.remove_empty_files = parse_content_rule(&value),
+                "remove_zero_filled" => config.remove_zero_filled = parse_content_rule(&value),
+                "in_progress" => {
+                    config.in_progress = match value {
+                        serde_yaml::Value::Bool(false) => None,
+                        serde_yaml::Value::Mapping(_) => {
+                            Some(serde_yaml::from_value(value).unwrap())
+                        }
+                        _ => Some(InProgressConfig::default()),
+                    }
+                }
+                "limits" => {
+                    config.limits = match value {
+                        serde_yaml::Value::Bool(false) => LimitsConfig::unlimited(),
+                        serde_yaml::Value::Mapping(_) => serde_yaml::from_value(value).unwrap(),
+                        _ => LimitsConfig::default(),
+                    }
+                }
+                "manifest" => {
+                    if let serde_yaml::Value::Mapping(_) = value {
+                        config.manifest = serde_yaml::from_value(value).unwrap();
+                    }
+                }
+                "sanitize" => {
+                    config.sanitize = match value {
+                        serde_yaml::Value::Bool(false) => None,
+                        serde_yaml::Value::String(profile) => Some(SanitizeConfig {
+                            profile,
+                            ..Default::default()
+                        }),
+                        serde_yaml::Value::Mapping(_) => {
+                            Some(serde_yaml::from_value(value).unwrap())
+                        }
+                        _ => Some(SanitizeConfig::default()),
+                    }
+                }
+                "flatten" => {
+                    config.flatten = match value {
+                        serde_yaml::Value::Bool(false) => None,
+                        serde_yaml::Value::Mapping(_) => {
+                            Some(serde_yaml::from_value(value).unwrap())
+                        }
+                        _ => Some(FlattenConfig::default()),
+                    }
+                }
                 _ => {}
             }
         }
         config
     }
 }
+
+/**
+ * 解析按内容删除的规则：`true` 匹配所有文件，`false` 关闭，
+ * 或者与 `remove` 相同格式的规则（多行字符串或序列）
+ */
+fn parse_content_rule(value: &serde_yaml::Value) -> Vec<String> {
+    match value {
+        serde_yaml::Value::Bool(true) => vec!["*".to_string()],
+        serde_yaml::Value::String(s) => s
+            .lines()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty() && !v.starts_with('#'))
+            .collect(),
+        serde_yaml::Value::Sequence(s) => {
+            s.iter().map(|v| v.as_str().unwrap().to_string()).collect()
+        }
+        _ => vec![],
+    }
+}
+
+/**
+ * 解析编码名称列表：逗号或空白分隔的字符串，或字符串序列
+ */
+fn parse_encoding_list(value: &serde_yaml::Value) -> Option<Vec<String>> {
+    match value {
+        serde_yaml::Value::String(s) => Some(
+            s.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+                .collect(),
+        ),
+        serde_yaml::Value::Sequence(s) => Some(
+            s.iter()
+                .map(|v| v.as_str().unwrap().to_string())
+                .collect::<Vec<_>>(),
+        ),
+        _ => None,
+    }
+}
 //EOP
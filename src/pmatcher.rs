@@ -1,25 +1,89 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fs::File;
 use std::io::{self, BufReader, Read};
 use std::path::{Path, PathBuf};
 
+use colored::*;
+use encoding_rs::Encoding;
 use fancy_regex::Regex;
 use indicatif::ProgressBar;
 use md5::{Digest, Md5};
 
+use crate::encoding;
 use crate::fnmatch_regex;
+use crate::fsops;
 use crate::pconfig;
+use crate::release;
+use crate::sanitize;
+use crate::template;
+use crate::zhconv;
 
 #[derive(Debug)]
 pub struct PatternMatcher {
     pub patterns_to_remove: Vec<Regex>,
     pub patterns_to_remove_with_hash: Vec<(Regex, Vec<String>)>,
     pub patterns_to_rename: Vec<Regex>,
+    pub patterns_to_organize: Vec<(Regex, String)>,
+    pub patterns_to_template: Vec<(Regex, String)>,
+    pub patterns_to_keep_manifest: Vec<Regex>,
+    pub patterns_to_skip: Vec<Regex>,
+    pub patterns_to_exclude: Vec<Regex>,
+    pub patterns_to_allow_large: Vec<Regex>,
+    pub transcode_encodings: Vec<&'static Encoding>,
+    pub repair_encodings: Vec<&'static Encoding>,
+    pub zh_normalize: bool,
+    pub zh_convert: Option<zhconv::Script>,
+    pub flatten: Option<pconfig::FlattenConfig>,
+    pub sidecar: Option<pconfig::SidecarConfig>,
+    pub in_progress: Option<pconfig::InProgressConfig>,
+    pub limits: pconfig::LimitsConfig,
+    pub patterns_to_remove_empty: Vec<Regex>,
+    pub patterns_to_remove_zero_filled: Vec<Regex>,
+    pub sanitize_profile: Option<sanitize::Profile>,
+    pub sanitize_replacement: String,
 }
 
 impl PatternMatcher {
     pub fn from_config_file(config_file: &Path) -> PatternMatcher {
-        let config = pconfig::PatternsConfig::from_config_file(config_file);
+        let mut config = pconfig::PatternsConfig::from_config_file(config_file);
+        let zh_config = config.zh_convert.clone().unwrap_or_default();
+        // 统一转为简体后匹配，使规则同时匹配简体和繁体
+        if zh_config.normalize {
+            for pattern in config.remove.iter_mut().chain(config.cleanup.iter_mut()) {
+                *pattern = zhconv::to_hans(pattern);
+            }
+            config.remove_hash = config
+                .remove_hash
+                .into_iter()
+                .map(|(pattern, hash_list)| (zhconv::to_hans(&pattern), hash_list))
+                .collect();
+        }
+        let zh_convert = zh_config.convert.as_deref().and_then(|name| {
+            let script = zhconv::Script::parse(name);
+            if script.is_none() {
+                eprintln!(
+                    "{} 无法识别的简繁转换目标，已忽略: {}",
+                    "[警告]".yellow(),
+                    name
+                );
+            }
+            script
+        });
+        let sanitize_config = config.sanitize.clone();
+        let sanitize_profile = sanitize_config.as_ref().and_then(|c| {
+            let profile = sanitize::Profile::parse(&c.profile);
+            if profile.is_none() {
+                eprintln!(
+                    "{} 无法识别的文件系统类型，已忽略: {}",
+                    "[警告]".yellow(),
+                    c.profile
+                );
+            }
+            profile
+        });
         let patterns_to_remove =
             create_mixed_regex_list(config.remove.iter().map(AsRef::as_ref).collect());
         let patterns_to_rename =
@@ -29,27 +93,110 @@ impl PatternMatcher {
             patterns_to_remove,
             patterns_to_remove_with_hash,
             patterns_to_rename,
+            patterns_to_organize: config
+                .organize
+                .iter()
+                .map(|rule| (Regex::new(rule.pattern.trim()).unwrap(), rule.dest.clone()))
+                .collect(),
+            patterns_to_template: config
+                .rename_template
+                .iter()
+                .map(|rule| {
+                    (
+                        Regex::new(rule.pattern.trim()).unwrap(),
+                        rule.template.clone(),
+                    )
+                })
+                .collect(),
+            patterns_to_skip: create_mixed_regex_list(
+                config.skip.iter().map(AsRef::as_ref).collect(),
+            ),
+            patterns_to_exclude: vec![],
+            patterns_to_allow_large: create_mixed_regex_list(
+                config.limits.allow_large.iter().map(AsRef::as_ref).collect(),
+            ),
+            patterns_to_keep_manifest: create_mixed_regex_list(
+                config.manifest.keep.iter().map(AsRef::as_ref).collect(),
+            ),
+            transcode_encodings: config
+                .transcode
+                .as_deref()
+                .map(encoding::parse_encodings)
+                .unwrap_or_default(),
+            repair_encodings: config
+                .repair_encoding
+                .as_deref()
+                .map(encoding::parse_encodings)
+                .unwrap_or_default(),
+            zh_normalize: zh_config.normalize,
+            zh_convert,
+            flatten: config.flatten,
+            sidecar: config.sidecar,
+            in_progress: config.in_progress,
+            limits: config.limits,
+            patterns_to_remove_empty: create_mixed_regex_list(
+                config.remove_empty_files.iter().map(AsRef::as_ref).collect(),
+            ),
+            patterns_to_remove_zero_filled: create_mixed_regex_list(
+                config.remove_zero_filled.iter().map(AsRef::as_ref).collect(),
+            ),
+            sanitize_profile,
+            sanitize_replacement: sanitize_config.map(|c| c.replacement).unwrap_or_default(),
         }
     }
 
     pub fn match_remove_pattern(&self, test_file: &str) -> (bool, Option<String>) {
+        let test_file = self.normalize(test_file);
         for re in &self.patterns_to_remove {
-            if re.is_match(test_file).unwrap() {
+            if re.is_match(&test_file).unwrap() {
                 return (true, Some(re.to_string()));
             }
         }
         (false, None) // return
     }
 
-    #[allow(dead_code)]
-    pub fn match_remove_hash(&self, test_file: &str) -> (bool, Option<String>) {
-        let filepath = Path::new(test_file);
-        let filename = match filepath.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name,
-            None => return (false, None), // 避免无效文件名
+    /**
+     * 添加命令行 `--exclude` 指定的通配符
+     */
+    pub fn add_exclude_patterns(&mut self, globs: &[String]) {
+        self.patterns_to_exclude.extend(globs.iter().map(|glob| {
+            Regex::new(fnmatch_regex::glob_to_regex_string(glob.trim()).as_str()).unwrap()
+        }));
+    }
+
+    /**
+     * 扫描时是否跳过（不进入）：匹配 `skip` 规则（`use_skip_list` 时）或 `--exclude` 通配符
+     */
+    pub fn match_skip(&self, filename: &str, use_skip_list: bool) -> bool {
+        let matches = |patterns: &[Regex]| patterns.iter().any(|re| re.is_match(filename).unwrap());
+        (use_skip_list && matches(&self.patterns_to_skip)) || matches(&self.patterns_to_exclude)
+    }
+
+    /**
+     * 是否有规则允许删除超过大小上限的文件
+     */
+    pub fn match_allow_large(&self, filename: &str) -> bool {
+        self.patterns_to_allow_large
+            .iter()
+            .any(|re| re.is_match(filename).unwrap())
+    }
+
+    /**
+     * 校验通过的校验文件是否按规则保留
+     */
+    pub fn match_keep_manifest(&self, filename: &str) -> bool {
+        self.patterns_to_keep_manifest
+            .iter()
+            .any(|re| re.is_match(filename).unwrap())
+    }
+
+    pub fn match_remove_hash(&self, filepath: &Path) -> (bool, Option<String>) {
+        let filename = match filepath.file_name() {
+            Some(name) => self.normalize(&encoding::escape_os_str(name)).into_owned(),
+            None => return (false, None),
         };
         for (re, hash_list) in &self.patterns_to_remove_with_hash {
-            if re.is_match(filename).unwrap() {
+            if re.is_match(&filename).unwrap() {
                 // 跳过大文件检查
                 if let Ok(metadata) = std::fs::metadata(filepath) {
                     if metadata.len() > 100 * 1024 * 1024 {
@@ -57,7 +204,7 @@ impl PatternMatcher {
                     }
                 }
                 // 处理 Result 类型
-                if let Ok(hash) = calculate_md5(test_file) {
+                if let Ok(hash) = calculate_md5(filepath) {
                     if hash_list.contains(&hash) {
                         return (true, Some(format!("{}:{}", re, hash)));
                     }
@@ -70,20 +217,19 @@ impl PatternMatcher {
     #[allow(dead_code)]
     pub fn match_remove_hash_with_progress(
         &self,
-        test_file: &str,
+        filepath: &Path,
         progress: Option<&ProgressBar>,
     ) -> (bool, Option<String>) {
-        let filepath = Path::new(test_file);
-        let filename = match filepath.file_name().and_then(|n| n.to_str()) {
-            Some(name) => name,
-            None => return (false, None), // 避免无效文件名
+        let filename = match filepath.file_name() {
+            Some(name) => self.normalize(&encoding::escape_os_str(name)).into_owned(),
+            None => return (false, None),
         };
 
         // 避免频繁更新和过长消息
         let mut last_update = std::time::Instant::now();
 
         for (re, hash_list) in &self.patterns_to_remove_with_hash {
-            if re.is_match(filename).unwrap() {
+            if re.is_match(&filename).unwrap() {
                 // 跳过大文件检查
                 if let Ok(metadata) = std::fs::metadata(filepath) {
                     if metadata.len() > 100 * 1024 * 1024 {
@@ -106,7 +252,7 @@ impl PatternMatcher {
                     }
                 }
 
-                if let Ok(hash) = calculate_md5(test_file) {
+                if let Ok(hash) = calculate_md5(filepath) {
                     if hash_list.contains(&hash) {
                         return (true, Some(format!("{}:{}", re, hash)));
                     }
@@ -116,40 +262,208 @@ impl PatternMatcher {
         (false, None)
     }
 
+    /**
+     * 按内容匹配删除规则：空文件（`remove_empty_files`）和全为零字节的文件（`remove_zero_filled`），
+     * 返回删除原因。规则匹配文件名或相对于 `target_path` 的路径；只检查普通文件，不跟随符号链接
+     */
+    pub fn match_remove_content(&self, filepath: &Path, target_path: &Path) -> Option<String> {
+        if self.patterns_to_remove_empty.is_empty()
+            && self.patterns_to_remove_zero_filled.is_empty()
+        {
+            return None;
+        }
+        let name = filepath.file_name()?.to_string_lossy();
+        let relative = filepath.strip_prefix(target_path).ok()?.to_string_lossy();
+        let matches = |patterns: &[Regex]| {
+            patterns
+                .iter()
+                .any(|re| re.is_match(&name).unwrap() || re.is_match(&relative).unwrap())
+        };
+        let remove_empty = matches(&self.patterns_to_remove_empty);
+        let remove_zero_filled = matches(&self.patterns_to_remove_zero_filled);
+        if !remove_empty && !remove_zero_filled {
+            return None;
+        }
+        let metadata = filepath.symlink_metadata().ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        if metadata.len() == 0 {
+            return remove_empty.then(|| "<EMPTY_FILE>".to_string());
+        }
+        if remove_zero_filled && fsops::is_zero_filled(filepath).unwrap_or(false) {
+            return Some("<ZERO_FILLED>".to_string());
+        }
+        None
+    }
+
+    /**
+     * 匹配整理规则，返回目标目录（已用命名捕获组渲染模板，捕获组之外的字段取自发布名解析结果），
+     * 以及目标目录必须位于其中的根目录：相对模板为 `base`，绝对模板为第一个占位符之前的目录（媒体库根目录）。
+     * 第一个匹配的规则生效；模板渲染失败或字段值不能作为目录名（空、`.`、`..`、包含 `/`）时输出警告并跳过该规则
+     */
+    pub fn match_organize(&self, filename: &str, base: &Path) -> Option<(PathBuf, PathBuf)> {
+        for (re, dest) in &self.patterns_to_organize {
+            if let Ok(Some(caps)) = re.captures(filename) {
+                let release = release::ReleaseInfo::parse(filename);
+                let invalid: RefCell<Option<String>> = RefCell::new(None);
+                let lookup = |name: &str| {
+                    let value = caps
+                        .name(name)
+                        .map(|m| m.as_str().to_string())
+                        .or_else(|| release.field(name))?;
+                    if value.is_empty() || value == "." || value == ".." || value.contains('/') {
+                        invalid.replace(Some(format!("{} = {:?}", name, value)));
+                        return None;
+                    }
+                    Some(value)
+                };
+                match template::render(dest, lookup) {
+                    Ok(rendered) => {
+                        let root = if Path::new(dest).is_absolute() {
+                            let literal = &dest[..dest.find('{').unwrap_or(dest.len())];
+                            let literal = &literal[..literal.rfind('/').map_or(0, |i| i + 1)];
+                            PathBuf::from(literal)
+                        } else {
+                            base.to_path_buf()
+                        };
+                        return Some((base.join(rendered), root));
+                    }
+                    Err(e) => eprintln!(
+                        "{} 整理规则模板渲染失败 {}: {} ({})",
+                        "[警告]".yellow(),
+                        filename,
+                        invalid
+                            .take()
+                            .map_or(e, |field| format!("字段值不能作为目录名: {}", field)),
+                        dest
+                    ),
+                }
+            }
+        }
+        None
+    }
+
+    /**
+     * 匹配模板重命名规则，用发布名解析出的字段渲染新文件名
+     * 没有匹配的规则时返回 None；解析出的字段不足以渲染模板时返回 Err（缺少的字段）
+     */
+    pub fn apply_rename_template(&self, filename: &str) -> Option<Result<String, String>> {
+        let (_, template) = self
+            .patterns_to_template
+            .iter()
+            .find(|(re, _)| re.is_match(filename).unwrap_or(false))?;
+        let release = release::ReleaseInfo::parse(filename);
+        Some(template::render(template, |name| release.field(name)))
+    }
+
     pub fn clean_filename(&self, filename: &str) -> String {
-        let mut new_filename = PathBuf::from(filename.to_string())
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
+        let mut new_filename = filename.to_string();
         for re in &self.patterns_to_rename {
-            new_filename = re.replace_all(&new_filename, "").to_string();
+            new_filename = if self.zh_normalize {
+                remove_matches(re, &new_filename, &zhconv::to_hans(&new_filename))
+            } else {
+                re.replace_all(&new_filename, "").to_string()
+            };
         }
-        let mut full_path = PathBuf::from(filename.to_string());
-        full_path.set_file_name(new_filename);
-        let new_filename = full_path.to_str().unwrap().to_string();
         new_filename // return new_filename
     }
+
+    /**
+     * 将重命名后的文件名转为配置的字形（简体或繁体），未配置时原样返回
+     */
+    pub fn convert_script(&self, filename: String) -> String {
+        match self.zh_convert {
+            Some(script) => zhconv::convert(&filename, script),
+            None => filename,
+        }
+    }
+
+    /**
+     * 使重命名后的文件名在目标文件系统上合法（未配置时不处理）；空文件名原样返回
+     */
+    pub fn sanitize_filename(&self, filename: String) -> String {
+        match self.sanitize_profile {
+            Some(profile) if !filename.is_empty() => {
+                sanitize::sanitize(&filename, profile, &self.sanitize_replacement)
+            }
+            _ => filename,
+        }
+    }
+
+    /// 开启简繁统一时，将文件名转为简体用于匹配
+    fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.zh_normalize {
+            Cow::Owned(zhconv::to_hans(text))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /**
+     * 将无效 UTF-8 的文件名按候选编码转码为有效的 UTF-8（未配置 `transcode` 时不转码），返回新文件名和所用编码
+     */
+    pub fn transcode_filename(&self, filename: &OsStr) -> Option<(String, &'static str)> {
+        if self.transcode_encodings.is_empty() {
+            return None;
+        }
+        encoding::transcode(filename, &self.transcode_encodings)
+    }
+
+    /**
+     * 修复乱码文件名（未配置 `repair_encoding` 时不修复），返回修复后的文件名、误用的编码和原始编码
+     */
+    pub fn repair_filename(&self, filename: &str) -> Option<(String, &'static str, &'static str)> {
+        if self.repair_encodings.is_empty() {
+            return None;
+        }
+        encoding::repair_mojibake(filename, &self.repair_encodings)
+    }
+}
+
+pub fn calculate_md5(filepath: &Path) -> io::Result<String> {
+    let mut hasher = Md5::new();
+    read_chunks(filepath, |chunk| hasher.update(chunk))?;
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
-fn calculate_md5(filepath: &str) -> io::Result<String> {
+/**
+ * 流式读取文件，依次把每块数据交给 `consume`（计算各种摘要时共用）
+ */
+pub fn read_chunks(filepath: &Path, mut consume: impl FnMut(&[u8])) -> io::Result<()> {
     let file = File::open(filepath)?;
     let mut reader = BufReader::with_capacity(8 * 1024 * 1024, file);
 
     // 使用堆分配的 Vec 代替栈上的大数组
     let mut buffer = vec![0; 64 * 1024]; // 64KB 缓冲区，在堆上分配
-    let mut hasher = Md5::new();
 
     loop {
         let bytes_read = reader.read(&mut buffer)?;
         if bytes_read == 0 {
             break;
         }
-        hasher.update(&buffer[..bytes_read]);
+        consume(&buffer[..bytes_read]);
     }
+    Ok(())
+}
 
-    Ok(format!("{:x}", hasher.finalize()))
+/**
+ * 在转换后的文本上匹配，从原文中删除对应的部分
+ * 逐字转换前后的字符数相同，按字符序号将匹配位置对应回原文
+ */
+fn remove_matches(re: &Regex, text: &str, normalized: &str) -> String {
+    let offsets =
+        |s: &str| -> Vec<usize> { s.char_indices().map(|(i, _)| i).chain([s.len()]).collect() };
+    let (text_offsets, normalized_offsets) = (offsets(text), offsets(normalized));
+    let to_text = |offset: usize| text_offsets[normalized_offsets.binary_search(&offset).unwrap()];
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for m in re.find_iter(normalized).flatten() {
+        result.push_str(&text[last..to_text(m.start())]);
+        last = to_text(m.end());
+    }
+    result.push_str(&text[last..]);
+    result
 }
 
 fn create_patterns_with_hash(patterns: HashMap<String, Vec<String>>) -> Vec<(Regex, Vec<String>)> {
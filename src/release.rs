@@ -0,0 +1,177 @@
+use std::sync::LazyLock;
+
+use fancy_regex::Regex;
+
+/// 开头的方括号组名，如 `[SubsPlease] Title - 01`
+static RE_LEADING_GROUP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*\[([^\]]+)\]\s*").unwrap());
+/// 季和集：S01E02、s1e2
+static RE_SEASON_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?<![0-9a-z])s(\d{1,2})[ ._-]?e(\d{1,3})(?![0-9])").unwrap());
+/// 季和集：1x02
+static RE_CROSS_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?<![0-9a-z])(\d{1,2})x(\d{2,3})(?![0-9a-z])").unwrap());
+/// 整季：S01
+static RE_SEASON: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?<![0-9a-z])s(\d{1,2})(?![0-9a-z])").unwrap());
+/// 动画常见的集数：Title - 01、Title - 01v2
+static RE_DASH_EPISODE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s-\s(\d{1,3})(?:v\d)?(?=\s|\[|\(|$)").unwrap());
+static RE_YEAR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?<![0-9A-Za-z])((?:19|20)\d{2})(?![0-9A-Za-z])").unwrap());
+static RE_RESOLUTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?<![0-9a-z])(\d{3,4}[pi]|4k|uhd)(?![0-9a-z])").unwrap()
+});
+static RE_CODEC: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?<![0-9a-z])(x26[45]|h\.?26[45]|hevc|avc|av1|xvid|divx)(?![0-9a-z])")
+        .unwrap()
+});
+/// 片源，只用于确定标题的结束位置
+static RE_SOURCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?<![0-9a-z])(web-?dl|webrip|web|blu-?ray|bdrip|brrip|hdtv|dvdrip|remux)(?![0-9a-z])")
+        .unwrap()
+});
+/// 结尾的发布组：-GROUP
+static RE_TRAILING_GROUP: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"-([A-Za-z0-9]+)$").unwrap());
+static RE_BRACKETS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[[^\]]*\]|【[^】]*】").unwrap());
+static RE_EXTENSION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9]{2,4}$").unwrap());
+
+/**
+ * 从 scene/P2P 发布名中解析出的结构化字段
+ */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReleaseInfo {
+    pub title: Option<String>,
+    pub year: Option<String>,
+    pub season: Option<String>,
+    pub episode: Option<String>,
+    pub resolution: Option<String>,
+    pub codec: Option<String>,
+    pub group: Option<String>,
+    pub ext: Option<String>,
+}
+
+impl ReleaseInfo {
+    /**
+     * 解析发布名，如 `My.Show.S01E02.1080p.WEB-DL.x264-GROUP.mkv`
+     * 或 `[Group] My Show - 02 [1080p].mkv`
+     */
+    pub fn parse(filename: &str) -> ReleaseInfo {
+        let mut info = ReleaseInfo::default();
+
+        // 扩展名：2~4 位字母数字，且不是纯数字（避免把年份等当作扩展名）
+        let mut name = filename;
+        if let Some((stem, ext)) = filename.rsplit_once('.') {
+            if !stem.is_empty()
+                && RE_EXTENSION.is_match(ext).unwrap_or(false)
+                && !ext.chars().all(|c| c.is_ascii_digit())
+            {
+                info.ext = Some(ext.to_string());
+                name = stem;
+            }
+        }
+
+        if let Ok(Some(caps)) = RE_LEADING_GROUP.captures(name) {
+            info.group = Some(caps[1].trim().to_string());
+            name = &name[caps.get(0).unwrap().end()..];
+        }
+
+        // 标题在第一个识别出的标记之前结束，发布组只能在最后一个标记之后
+        let mut title_end = name.len();
+        let mut marks_end = 0;
+        let mut mark = |start: usize, end: usize| {
+            title_end = title_end.min(start);
+            marks_end = marks_end.max(end);
+        };
+
+        if let Ok(Some(caps)) = RE_SEASON_EPISODE
+            .captures(name)
+            .map(|c| c.or_else(|| RE_CROSS_EPISODE.captures(name).ok().flatten()))
+        {
+            info.season = Some(trim_number(&caps[1]));
+            info.episode = Some(trim_number(&caps[2]));
+            let m = caps.get(0).unwrap();
+            mark(m.start(), m.end());
+        } else if let Ok(Some(caps)) = RE_SEASON.captures(name) {
+            info.season = Some(trim_number(&caps[1]));
+            let m = caps.get(0).unwrap();
+            mark(m.start(), m.end());
+        } else if let Ok(Some(caps)) = RE_DASH_EPISODE.captures(name) {
+            info.episode = Some(trim_number(&caps[1]));
+            let m = caps.get(0).unwrap();
+            mark(m.start(), m.end());
+        }
+        // 年份不能在开头，避免把以年份命名的标题（如 2012）当作年份
+        for m in RE_YEAR.find_iter(name).flatten() {
+            if m.start() > 0 {
+                info.year = Some(m.as_str().to_string());
+                mark(m.start(), m.end());
+                break;
+            }
+        }
+        for m in RE_RESOLUTION.find_iter(name).flatten() {
+            info.resolution.get_or_insert_with(|| m.as_str().to_string());
+            mark(m.start(), m.end());
+        }
+        for m in RE_CODEC.find_iter(name).flatten() {
+            info.codec.get_or_insert_with(|| m.as_str().to_string());
+            mark(m.start(), m.end());
+        }
+        for m in RE_SOURCE.find_iter(name).flatten() {
+            mark(m.start(), m.end());
+        }
+
+        // 有其他标记时，最后一个标记之后结尾的 -XXX 才视为发布组
+        // （避免把 Spider-Man 这样的标题或 WEB-DL 这样的片源拆开）
+        if info.group.is_none() && title_end < name.len() {
+            if let Ok(Some(caps)) = RE_TRAILING_GROUP.captures(name) {
+                if caps.get(0).unwrap().start() >= marks_end {
+                    info.group = Some(caps[1].to_string());
+                }
+            }
+        }
+
+        let title = RE_BRACKETS.replace_all(&name[..title_end], " ");
+        let title = title
+            .replace(['.', '_'], " ")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        let title = title.trim_matches(|c: char| c == '-' || c == '(' || c == '[' || c.is_whitespace());
+        if !title.is_empty() {
+            info.title = Some(title.to_string());
+        }
+        info
+    }
+
+    /**
+     * 按名称获取字段，用于模板渲染
+     */
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "title" => self.title.clone(),
+            "year" => self.year.clone(),
+            "season" => self.season.clone(),
+            "episode" => self.episode.clone(),
+            "resolution" => self.resolution.clone(),
+            "codec" => self.codec.clone(),
+            "group" => self.group.clone(),
+            "ext" => self.ext.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// 去掉数字的前导零（保留至少一位），补零交给模板的格式说明
+fn trim_number(number: &str) -> String {
+    let trimmed = number.trim_start_matches('0');
+    if trimmed.is_empty() {
+        "0".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+//EOP
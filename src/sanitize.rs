@@ -0,0 +1,119 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::util;
+
+/// 文件名的最大长度（UTF-8 字节数）。Windows 和 exFAT 按 UTF-16 计算的上限是 255，
+/// UTF-8 的字节数不会少于 UTF-16 的单元数，因此 255 字节对所有目标都是安全的
+pub const NAME_MAX: usize = 255;
+/// 扩展名超过这个长度时不视为扩展名，截断时不保留
+const EXTENSION_MAX: usize = 16;
+
+/// 文件名需要兼容的目标文件系统
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    /// Linux 等：只限制长度
+    Posix,
+    /// Windows / SMB：非法字符、结尾的点和空格、保留名称
+    Windows,
+    /// exFAT：非法字符、结尾的点和空格
+    Exfat,
+}
+
+impl Profile {
+    pub fn parse(name: &str) -> Option<Profile> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "posix" => Some(Profile::Posix),
+            "windows" | "smb" => Some(Profile::Windows),
+            "exfat" => Some(Profile::Exfat),
+            _ => None,
+        }
+    }
+
+    fn is_illegal(&self, c: char) -> bool {
+        match self {
+            Profile::Posix => c == '/' || c == '\0',
+            Profile::Windows | Profile::Exfat => {
+                c.is_control() || matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*')
+            }
+        }
+    }
+}
+
+/**
+ * 使文件名在目标文件系统上合法：替换非法字符（`replacement` 为空时删除），
+ * 去掉结尾的点和空格，避开 Windows 的保留名称（如 `CON` 改为 `CON_`），最后按长度截断
+ */
+pub fn sanitize(name: &str, profile: Profile, replacement: &str) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if profile.is_illegal(c) {
+            sanitized.push_str(replacement);
+        } else {
+            sanitized.push(c);
+        }
+    }
+    if profile != Profile::Posix {
+        sanitized.truncate(sanitized.trim_end_matches(['.', ' ']).len());
+    }
+    if profile == Profile::Windows && is_reserved(&sanitized) {
+        // 保留名称只看第一个点之前的部分，如 `con.tar.gz`
+        let end = sanitized.find('.').unwrap_or(sanitized.len());
+        sanitized.insert(end, '_');
+    }
+    if sanitized.is_empty() && !name.is_empty() {
+        sanitized.push('_');
+    }
+    let truncated = truncate_name(&sanitized, NAME_MAX);
+    if profile != Profile::Posix {
+        truncated.trim_end_matches(['.', ' ']).to_string()
+    } else {
+        truncated
+    }
+}
+
+/**
+ * 将文件名截断到不超过 `max_bytes` 字节（UTF-8），保留扩展名，只在字素边界截断
+ */
+pub fn truncate_name(name: &str, max_bytes: usize) -> String {
+    if name.len() <= max_bytes {
+        return name.to_string();
+    }
+    let (stem, ext) = match util::split_extension(name) {
+        (stem, ext) if !stem.is_empty() && ext.len() <= EXTENSION_MAX => (stem, ext),
+        _ => (name, ""),
+    };
+    format!(
+        "{}{}",
+        truncate_graphemes(stem, max_bytes.saturating_sub(ext.len())),
+        ext
+    )
+}
+
+/**
+ * 截取不超过 `max_bytes` 字节的前缀，不拆开字素（如带组合符号的字符、emoji 序列）
+ */
+pub fn truncate_graphemes(text: &str, max_bytes: usize) -> &str {
+    let mut end = 0;
+    for (index, grapheme) in text.grapheme_indices(true) {
+        if index + grapheme.len() > max_bytes {
+            break;
+        }
+        end = index + grapheme.len();
+    }
+    &text[..end]
+}
+
+/// Windows 的保留设备名称（不区分大小写，可带扩展名）
+fn is_reserved(name: &str) -> bool {
+    let base = name
+        .split('.')
+        .next()
+        .unwrap_or("")
+        .trim_end()
+        .to_ascii_uppercase();
+    matches!(base.as_str(), "CON" | "PRN" | "AUX" | "NUL")
+        || (base.len() == 4
+            && (base.starts_with("COM") || base.starts_with("LPT"))
+            && base.as_bytes()[3].is_ascii_digit())
+}
+//EOP
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::data::Operation;
+use crate::pconfig::SidecarConfig;
+use crate::sanitize;
+use crate::util;
+
+/// 默认的媒体文件扩展名
+pub const DEFAULT_MEDIA_EXTENSIONS: [&str; 12] = [
+    "mkv", "mp4", "avi", "ts", "m2ts", "wmv", "mov", "flv", "webm", "rmvb", "mpg", "iso",
+];
+/// 默认的附属文件扩展名
+pub const DEFAULT_SIDECAR_EXTENSIONS: [&str; 11] = [
+    "srt", "ass", "ssa", "sub", "idx", "sup", "vtt", "nfo", "jpg", "jpeg", "png",
+];
+
+/**
+ * 找出媒体文件的附属文件：与媒体文件在同一目录，文件名以媒体文件的主名开头，
+ * 之后是 `.` 或 `-`（如 `Movie.zh.srt`、`Movie-poster.jpg`），扩展名属于附属文件扩展名。
+ * 返回 附属文件 => (媒体文件, 主名之后的部分)；有多个媒体文件符合时取主名最长的一个
+ */
+pub fn find_sidecars(
+    paths: &[PathBuf],
+    config: &SidecarConfig,
+) -> HashMap<PathBuf, (PathBuf, String)> {
+    let has_extension = |name: &str, extensions: &[String]| {
+        let (_, ext) = util::split_extension(name);
+        !ext.is_empty() && extensions.iter().any(|e| e.eq_ignore_ascii_case(&ext[1..]))
+    };
+
+    // 按目录收集媒体文件的主名
+    let mut media_stems: HashMap<&Path, Vec<(&str, &PathBuf)>> = HashMap::new();
+    for path in paths {
+        if let (Some(parent), Some(name)) =
+            (path.parent(), path.file_name().and_then(|n| n.to_str()))
+        {
+            if has_extension(name, &config.media) && path.is_file() {
+                media_stems
+                    .entry(parent)
+                    .or_default()
+                    .push((util::split_extension(name).0, path));
+            }
+        }
+    }
+
+    let mut sidecars = HashMap::new();
+    for path in paths {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name().and_then(|n| n.to_str()))
+        else {
+            continue;
+        };
+        if !has_extension(name, &config.extensions) || !path.is_file() {
+            continue;
+        }
+        let Some(stems) = media_stems.get(parent) else {
+            continue;
+        };
+        let media = stems
+            .iter()
+            .filter(|(stem, _)| {
+                name.strip_prefix(stem)
+                    .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('-'))
+            })
+            .max_by_key(|(stem, _)| stem.len());
+        if let Some((stem, media_path)) = media {
+            sidecars.insert(
+                path.clone(),
+                ((*media_path).clone(), name[stem.len()..].to_string()),
+            );
+        }
+    }
+    sidecars
+}
+
+/**
+ * 附属文件跟随媒体文件：媒体文件重命名或移动时，附属文件使用媒体文件的新主名并一起移动。
+ * 附属文件自身的清理结果仍以媒体文件原来或新的主名开头时，使用清理后的部分作为后缀（如 `Movie.[site.com].zh.srt` => `.zh.srt`）；
+ * 媒体文件不变时，附属文件保留自身的重命名，但不单独移动，以免与媒体文件分开。
+ * 要删除的附属文件，以及媒体文件要删除时的附属文件，保持原来的操作。
+ * 返回跟随重命名或移动的附属文件，执行时按媒体文件的最终名称确定它们的目标（见 `resolve_targets`）
+ */
+pub fn follow_media(
+    file_info: &mut HashMap<PathBuf, (String, Operation)>,
+    sidecars: &HashMap<PathBuf, (PathBuf, String)>,
+) -> HashMap<PathBuf, (PathBuf, String)> {
+    let mut followers = HashMap::new();
+    for (sidecar, (media, suffix)) in sidecars {
+        let media_op = match file_info.get(media) {
+            Some((pattern, op)) => (pattern.clone(), op.clone()),
+            None => continue,
+        };
+        let Some((sidecar_name, sidecar_op)) = file_info.get(sidecar).cloned() else {
+            continue;
+        };
+        if !matches!(
+            sidecar_op,
+            Operation::None | Operation::Rename | Operation::Move
+        ) {
+            continue;
+        }
+        // 附属文件清理后的名称以媒体文件原来或新的主名开头时，取其后的部分作为后缀
+        let mut media_stems = vec![media
+            .file_name()
+            .map(|n| util::split_extension(&n.to_string_lossy()).0.to_string())
+            .unwrap_or_default()];
+        if media_op.1 == Operation::Rename {
+            media_stems.push(util::split_extension(&media_op.0).0.to_string());
+        }
+        let suffix = match sidecar_op {
+            Operation::Rename => media_stems
+                .iter()
+                .filter_map(|stem| sidecar_name.strip_prefix(stem.as_str()))
+                .find(|rest| rest.starts_with('.') || rest.starts_with('-'))
+                .unwrap_or(suffix)
+                .to_string(),
+            _ => suffix.clone(),
+        };
+        let followed = match media_op {
+            (new_name, Operation::Rename) => {
+                let new_name = sanitize::truncate_name(
+                    &format!("{}{}", util::split_extension(&new_name).0, suffix),
+                    sanitize::NAME_MAX,
+                );
+                if sidecar.file_name().is_some_and(|n| n == new_name.as_str()) {
+                    ("".to_string(), Operation::None)
+                } else {
+                    (new_name, Operation::Rename)
+                }
+            }
+            (dest, Operation::Move) => {
+                let dest = PathBuf::from(dest);
+                let stem = dest
+                    .file_name()
+                    .map(|n| util::split_extension(&n.to_string_lossy()).0.to_string())
+                    .unwrap_or_default();
+                let sidecar_dest = dest.with_file_name(sanitize::truncate_name(
+                    &format!("{}{}", stem, suffix),
+                    sanitize::NAME_MAX,
+                ));
+                (sidecar_dest.to_string_lossy().into_owned(), Operation::Move)
+            }
+            // 媒体文件不变：附属文件自身的重命名照常执行，不参与跟随
+            (_, Operation::None) if sidecar_op == Operation::Rename => continue,
+            (_, Operation::None) => ("".to_string(), Operation::None),
+            _ => continue,
+        };
+        if followed.1 != Operation::None {
+            followers.insert(sidecar.clone(), (media.clone(), suffix));
+        }
+        file_info.insert(sidecar.clone(), followed);
+    }
+    followers
+}
+
+/**
+ * 按媒体文件实际的最终位置（可能带有冲突后缀）计算附属文件的目标路径，返回 (附属文件, 目标路径)。
+ * 媒体文件没有被重命名或移动（如执行失败）时，附属文件保持原样
+ */
+pub fn resolve_targets(
+    followers: &HashMap<PathBuf, (PathBuf, String)>,
+    moves: &[(PathBuf, PathBuf)],
+) -> Vec<(PathBuf, PathBuf)> {
+    let mut targets = Vec::new();
+    for (sidecar, (media, suffix)) in followers {
+        let media_path = util::remap_path(media, moves);
+        if media_path == *media || media_path.as_os_str().is_empty() {
+            continue;
+        }
+        let Some(name) = media_path.file_name() else {
+            continue;
+        };
+        let stem = util::split_extension(&name.to_string_lossy()).0.to_string();
+        let target = media_path.with_file_name(sanitize::truncate_name(
+            &format!("{}{}", stem, suffix),
+            sanitize::NAME_MAX,
+        ));
+        targets.push((sidecar.clone(), target));
+    }
+    targets.sort();
+    targets
+}
+//EOP
@@ -0,0 +1,63 @@
+/**
+ * 简单的字符串模板
+ *
+ * `{name}` 替换为字段值，`{name:02}` 将数字字段补零到指定宽度，`{{` 和 `}}` 表示字面的花括号。
+ * 模板中引用的字段不存在时返回错误（包含字段名）。
+ */
+pub fn render(template: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                result.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                result.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(format!("未闭合的占位符: {{{}", placeholder)),
+                    }
+                }
+                let (name, spec) = match placeholder.split_once(':') {
+                    Some((name, spec)) => (name.trim(), Some(spec.trim())),
+                    None => (placeholder.trim(), None),
+                };
+                let value = lookup(name).ok_or_else(|| format!("缺少字段: {}", name))?;
+                result.push_str(&format_value(&value, spec)?);
+            }
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+/**
+ * 按格式说明格式化字段值：`0N` 补零到 N 位（仅数字），`N` 右侧补空格到 N 位
+ */
+fn format_value(value: &str, spec: Option<&str>) -> Result<String, String> {
+    let spec = match spec {
+        None | Some("") => return Ok(value.to_string()),
+        Some(spec) => spec,
+    };
+    let width: usize = spec
+        .parse()
+        .map_err(|_| format!("无效的格式说明: {}", spec))?;
+    if spec.starts_with('0') {
+        let number: u64 = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("字段值不是数字，无法补零: {}", value))?;
+        Ok(format!("{:0width$}", number, width = width))
+    } else {
+        Ok(format!("{:width$}", value, width = width))
+    }
+}
+//EOP
@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::{OsStr, OsString};
+use std::fs::File;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::FileExt;
+use std::path::{Component, Path, PathBuf};
+
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use sha1::{Digest, Sha1};
+
+use crate::bencode;
+use crate::util;
+
+/// 允许的最大分块大小（常见的种子不超过 16 MiB），防止异常的种子文件
+const MAX_PIECE_LENGTH: u64 = 256 << 20;
+/// 校验时读取文件使用的缓冲区大小
+const READ_BUFFER_SIZE: usize = 64 << 10;
+
+/// 种子中的一个文件，路径相对于内容根目录
+#[derive(Debug, Clone)]
+pub struct TorrentFile {
+    pub path: PathBuf,
+    pub length: u64,
+    /// BEP 47 填充文件（`attr` 包含 `p`）
+    pub padding: bool,
+}
+
+/**
+ * 解析后的 .torrent 文件（只包含清理需要的字段）
+ */
+#[derive(Debug, Clone)]
+pub struct Torrent {
+    pub name: OsString,
+    /// 单文件种子：内容就是名为 `name` 的文件
+    pub single_file: bool,
+    pub files: Vec<TorrentFile>,
+    pub piece_length: u64,
+    /// 每个分块的 SHA-1
+    pub pieces: Vec<[u8; 20]>,
+}
+
+/// 种子内容的校验结果
+#[derive(Debug)]
+pub struct Verification {
+    pub total_pieces: usize,
+    pub failed_pieces: usize,
+    /// 与损坏分块有重叠的文件（不含填充文件）
+    pub damaged_files: Vec<PathBuf>,
+}
+
+impl Torrent {
+    pub fn from_file(path: &Path) -> Result<Torrent, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let root = bencode::decode(&data)?;
+        let info = root.get("info").ok_or("缺少 info")?;
+
+        let name = utf8_or_raw(info, "name").ok_or("缺少 name")?;
+        let name = safe_component(name).ok_or("无效的 name")?;
+        let (single_file, files) = match info.get("files").and_then(bencode::Value::as_list) {
+            Some(list) => {
+                let files = list
+                    .iter()
+                    .map(parse_file)
+                    .collect::<Result<Vec<_>, String>>()?;
+                (false, files)
+            }
+            None => {
+                let length = info
+                    .get("length")
+                    .and_then(bencode::Value::as_int)
+                    .filter(|n| *n >= 0)
+                    .ok_or("缺少 length")? as u64;
+                let file = TorrentFile {
+                    path: PathBuf::from(&name),
+                    length,
+                    padding: false,
+                };
+                (true, vec![file])
+            }
+        };
+        let piece_length = info
+            .get("piece length")
+            .and_then(bencode::Value::as_int)
+            .ok_or("缺少 piece length")?;
+        if piece_length <= 0 || piece_length as u64 > MAX_PIECE_LENGTH {
+            return Err(format!("无效的 piece length: {}", piece_length));
+        }
+        let piece_length = piece_length as u64;
+        let pieces = info
+            .get("pieces")
+            .and_then(bencode::Value::as_bytes)
+            .filter(|p| p.len() % 20 == 0)
+            .ok_or("无效的 pieces")?;
+        let pieces: Vec<[u8; 20]> = pieces
+            .chunks_exact(20)
+            .map(|c| c.try_into().unwrap())
+            .collect();
+        let total = files
+            .iter()
+            .try_fold(0u64, |total, f| total.checked_add(f.length))
+            .ok_or("文件总大小溢出")?;
+        if pieces.len() as u64 != total.div_ceil(piece_length) {
+            return Err("分块数量与文件大小不一致".to_string());
+        }
+        Ok(Torrent {
+            name,
+            single_file,
+            files,
+            piece_length,
+            pieces,
+        })
+    }
+
+    /**
+     * 查找种子内容在磁盘上的位置：与 .torrent 文件同一目录下名为 `name` 的目录（或单文件），
+     * 或者 .torrent 文件就在内容目录之中。找不到时返回 None。
+     * 内容根目录就是目标路径时（种子在目标路径旁边），使用与扫描结果相同的路径形式
+     */
+    pub fn content_root(&self, torrent_path: &Path, target_path: &Path) -> Option<PathBuf> {
+        let dir = torrent_path.parent()?;
+        let candidate = dir.join(&self.name);
+        let root = if self.single_file {
+            candidate.is_file().then_some(candidate)?
+        } else if candidate.is_dir() {
+            candidate
+        } else if dir.file_name() == Some(self.name.as_os_str()) {
+            dir.to_path_buf()
+        } else {
+            return None;
+        };
+        if util::is_same_file(&root, target_path) {
+            Some(target_path.to_path_buf())
+        } else {
+            Some(root)
+        }
+    }
+
+    /**
+     * 种子中文件在磁盘上的路径：单文件种子的内容根就是文件本身
+     */
+    pub fn file_path(&self, root: &Path, file: &TorrentFile) -> PathBuf {
+        if self.single_file {
+            root.to_path_buf()
+        } else {
+            root.join(&file.path)
+        }
+    }
+}
+
+/**
+ * 查找要解析的 .torrent 文件：目标路径中的，以及目标路径旁边（上一级目录中）的
+ */
+pub fn find_torrent_files(paths: &[PathBuf], target_path: &Path) -> Vec<PathBuf> {
+    let is_torrent = |path: &Path| {
+        path.extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("torrent"))
+            && path.is_file()
+    };
+    let mut torrents: Vec<PathBuf> = paths.iter().filter(|p| is_torrent(p)).cloned().collect();
+    if let Some(parent) = target_path
+        .canonicalize()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+    {
+        if let Ok(entries) = parent.read_dir() {
+            torrents.extend(
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| is_torrent(p)),
+            );
+        }
+    }
+    torrents
+}
+
+/**
+ * 根据种子文件列表规划要删除的文件：BEP 47 填充文件，以及（`flag_foreign` 时）
+ * 在内容目录中但不在种子文件列表中的外来文件。返回 路径 => 删除原因
+ */
+pub fn plan_junk(
+    torrents: &[(PathBuf, Torrent)],
+    paths: &[PathBuf],
+    target_path: &Path,
+    flag_foreign: bool,
+) -> HashMap<PathBuf, String> {
+    let mut junk = HashMap::new();
+    let torrent_paths: HashSet<&PathBuf> = torrents.iter().map(|(path, _)| path).collect();
+    for (torrent_path, torrent) in torrents {
+        let Some(root) = torrent.content_root(torrent_path, target_path) else {
+            continue;
+        };
+        let mut listed: HashSet<PathBuf> = HashSet::new();
+        for file in &torrent.files {
+            let path = torrent.file_path(&root, file);
+            // 大小与种子中记录的一致才删除，避免误删同名的其他文件
+            if file.padding
+                && path
+                    .symlink_metadata()
+                    .is_ok_and(|m| m.is_file() && m.len() == file.length)
+            {
+                junk.insert(path.clone(), "<TORRENT_PADDING>".to_string());
+            }
+            listed.insert(path);
+        }
+        if flag_foreign && !torrent.single_file {
+            for path in paths {
+                if path.starts_with(&root)
+                    && path.is_file()
+                    && !listed.contains(path)
+                    && !torrent_paths.contains(path)
+                {
+                    junk.entry(path.clone())
+                        .or_insert_with(|| "<TORRENT_FOREIGN>".to_string());
+                }
+            }
+        }
+    }
+    junk
+}
+
+/**
+ * 按种子中的 SHA-1 校验内容：分块可能跨越多个文件，填充文件不读取磁盘，按全零计算。
+ * 文件缺失或长度不足时，相关分块视为损坏。各分块并行校验，每完成一个分块进度条加一
+ */
+pub fn verify(torrent: &Torrent, root: &Path, progress: &ProgressBar) -> Verification {
+    // 每个文件在整个种子数据中的起始偏移
+    let mut offsets = Vec::with_capacity(torrent.files.len());
+    let mut offset = 0;
+    for file in &torrent.files {
+        offsets.push(offset);
+        offset += file.length;
+    }
+    let total_length = offset;
+
+    let failed: Vec<usize> = (0..torrent.pieces.len())
+        .into_par_iter()
+        .filter(|&index| {
+            let start = index as u64 * torrent.piece_length;
+            let end = (start + torrent.piece_length).min(total_length);
+            let ok = hash_range(torrent, root, &offsets, start, end)
+                .is_some_and(|hash| hash == torrent.pieces[index]);
+            progress.inc(1);
+            !ok
+        })
+        .collect();
+
+    let mut damaged_files = Vec::new();
+    for (file, &file_start) in torrent.files.iter().zip(&offsets) {
+        let file_end = file_start + file.length;
+        let damaged = !file.padding
+            && failed.iter().any(|&index| {
+                let start = index as u64 * torrent.piece_length;
+                start < file_end && file_start < start + torrent.piece_length
+            });
+        if damaged {
+            damaged_files.push(torrent.file_path(root, file));
+        }
+    }
+    Verification {
+        total_pieces: torrent.pieces.len(),
+        failed_pieces: failed.len(),
+        damaged_files,
+    }
+}
+
+/// 逐块读取并计算种子数据中 [start, end) 范围的 SHA-1（填充文件按全零计算），无法读取时返回 None
+fn hash_range(
+    torrent: &Torrent,
+    root: &Path,
+    offsets: &[u64],
+    start: u64,
+    end: u64,
+) -> Option<[u8; 20]> {
+    let mut hasher = Sha1::new();
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+    for (file, &file_start) in torrent.files.iter().zip(offsets) {
+        let file_end = file_start + file.length;
+        if file_end <= start || file_start >= end {
+            continue;
+        }
+        let from = start.max(file_start);
+        let to = end.min(file_end);
+        let opened = if file.padding {
+            None
+        } else {
+            Some(File::open(torrent.file_path(root, file)).ok()?)
+        };
+        let mut position = from;
+        while position < to {
+            let len = (to - position).min(READ_BUFFER_SIZE as u64) as usize;
+            let chunk = &mut buffer[..len];
+            match &opened {
+                Some(f) => f.read_exact_at(chunk, position - file_start).ok()?,
+                None => chunk.fill(0),
+            }
+            hasher.update(&*chunk);
+            position += len as u64;
+        }
+    }
+    Some(hasher.finalize().into())
+}
+
+fn parse_file(value: &bencode::Value) -> Result<TorrentFile, String> {
+    let length = value
+        .get("length")
+        .and_then(bencode::Value::as_int)
+        .filter(|n| *n >= 0)
+        .ok_or("文件缺少 length")? as u64;
+    let components = value
+        .get("path.utf-8")
+        .or_else(|| value.get("path"))
+        .and_then(bencode::Value::as_list)
+        .ok_or("文件缺少 path")?;
+    let mut path = PathBuf::new();
+    for component in components {
+        let component = component.as_bytes().ok_or("无效的 path")?;
+        path.push(
+            safe_component(OsStr::from_bytes(component).to_os_string()).ok_or("无效的 path")?,
+        );
+    }
+    if path.as_os_str().is_empty() {
+        return Err("文件 path 为空".to_string());
+    }
+    let padding = value
+        .get("attr")
+        .and_then(bencode::Value::as_bytes)
+        .is_some_and(|attr| attr.contains(&b'p'));
+    Ok(TorrentFile {
+        path,
+        length,
+        padding,
+    })
+}
+
+/// 优先使用 `<key>.utf-8` 字段
+fn utf8_or_raw(value: &bencode::Value, key: &str) -> Option<OsString> {
+    value
+        .get(&format!("{}.utf-8", key))
+        .or_else(|| value.get(key))
+        .and_then(bencode::Value::as_bytes)
+        .map(|bytes| OsStr::from_bytes(bytes).to_os_string())
+}
+
+/// 只接受普通的单层路径名，拒绝 `..`、绝对路径和包含分隔符的名称
+fn safe_component(name: OsString) -> Option<OsString> {
+    let mut components = Path::new(&name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.as_bytes().contains(&b'/') => Some(name),
+        _ => None,
+    }
+}
+//EOP
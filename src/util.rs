@@ -1,8 +1,11 @@
 use dirs_next as dirs;
+use indicatif::{ProgressBar, ProgressStyle};
 use std::env;
 use std::fs::{remove_dir_all, remove_file};
-use std::path::{Path, PathBuf};
-use walkdir::DirEntry;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+use std::path::{Component, Path, PathBuf};
+
+use crate::sanitize;
 
 pub fn remove_path(path: PathBuf) -> std::io::Result<()> {
     match remove_file(&path) {
@@ -19,14 +22,6 @@ pub fn get_guess_paths(target_path: &Path) -> Vec<PathBuf> {
     guess_paths
 }
 
-pub fn is_not_hidden(entry: &DirEntry) -> bool {
-    entry.file_name().to_string_lossy() != ".tmp"
-        && entry.path().parent().map_or(true, |p| {
-            p.file_name()
-                .map_or(true, |p| p.to_string_lossy() != ".tmp")
-        })
-}
-
 pub fn guess_path(test_file: &str, mut guess_paths: Vec<PathBuf>) -> Option<PathBuf> {
     if guess_paths.is_empty() {
         if let Ok(cwd) = env::current_dir() {
@@ -54,4 +49,158 @@ pub fn dedup_vec(v: &Vec<PathBuf>) -> Vec<PathBuf> {
     }
     new_vec // return new_vec;
 }
+
+/**
+ * 分离文件名和扩展名（扩展名包含点号）
+ */
+pub fn split_extension(filename: &str) -> (&str, &str) {
+    match filename.rfind('.') {
+        Some(dot_pos) => (&filename[..dot_pos], &filename[dot_pos..]),
+        None => (filename, ""),
+    }
+}
+
+/**
+ * 为冲突的文件名寻找可用的名称：name(1).ext, name(2).ext, ...
+ * `is_free` 判断候选名称是否可用，最多尝试 999 个后缀；加上后缀超出长度限制时截断 name 部分
+ */
+pub fn find_available_name(filename: &str, is_free: impl Fn(&str) -> bool) -> Option<String> {
+    if is_free(filename) {
+        return Some(filename.to_string());
+    }
+    let (name_without_ext, extension) = split_extension(filename);
+    (1..=999)
+        .map(|counter| {
+            let suffix = format!("({}){}", counter, extension);
+            let name = sanitize::truncate_graphemes(
+                name_without_ext,
+                sanitize::NAME_MAX.saturating_sub(suffix.len()),
+            );
+            format!("{}{}", name, suffix)
+        })
+        .find(|new_name| is_free(new_name))
+}
+
+/**
+ * 两个路径是否指向同一个文件（相同的设备和 inode），不跟随符号链接
+ */
+pub fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (a.symlink_metadata(), b.symlink_metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/**
+ * 目录中恰好只有一个条目时返回该条目
+ */
+pub fn single_child(dir: &Path) -> Option<PathBuf> {
+    let mut entries = dir.read_dir().ok()?;
+    let child = entries.next()?.ok()?;
+    match entries.next() {
+        None => Some(child.path()),
+        Some(_) => None,
+    }
+}
+
+/**
+ * 按移动记录（依次应用）计算路径的新位置
+ */
+pub fn remap_path(path: &Path, moves: &[(PathBuf, PathBuf)]) -> PathBuf {
+    let mut path = path.to_path_buf();
+    for (from, to) in moves {
+        if let Ok(rest) = path.strip_prefix(from) {
+            path = if rest.as_os_str().is_empty() {
+                to.clone()
+            } else {
+                to.join(rest)
+            };
+        }
+    }
+    path
+}
+
+/**
+ * 按字面规范化路径：去掉 `.`，`..` 与前一级相抵（不访问文件系统，不解析符号链接）
+ */
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// 不允许直接清理的系统目录（精确匹配，其中的子目录不受限制）
+const DANGEROUS_ROOTS: [&str; 22] = [
+    "/", "/bin", "/boot", "/dev", "/etc", "/home", "/lib", "/lib64", "/media", "/mnt", "/opt",
+    "/proc", "/root", "/run", "/sbin", "/srv", "/sys", "/tmp", "/usr", "/var", "/Users", "/Volumes",
+];
+
+/**
+ * 目标路径是否为危险的根目录：系统目录、用户主目录，或者挂载点（整个磁盘、分区）。
+ * 返回原因，`path` 应为规范化后的路径
+ */
+pub fn dangerous_root(path: &Path) -> Option<&'static str> {
+    let is_same = |other: &Path| other.canonicalize().is_ok_and(|other| other == path);
+    if DANGEROUS_ROOTS.iter().any(|root| is_same(Path::new(root))) {
+        return Some("系统目录");
+    }
+    if dirs::home_dir().is_some_and(|home| is_same(&home)) {
+        return Some("用户主目录");
+    }
+    let parent = path.parent()?;
+    match (path.metadata(), parent.metadata()) {
+        (Ok(a), Ok(b)) if a.dev() != b.dev() => Some("挂载点"),
+        _ => None,
+    }
+}
+
+/**
+ * 路径是否存在（不跟随符号链接，失效的符号链接也视为存在）
+ */
+pub fn path_exists(path: &Path) -> bool {
+    path.symlink_metadata().is_ok()
+}
+
+/**
+ * 特殊文件（命名管道、套接字、设备文件）的类型名称，普通文件、目录和符号链接返回 None
+ */
+pub fn special_file_kind(file_type: &std::fs::FileType) -> Option<&'static str> {
+    if file_type.is_fifo() {
+        Some("命名管道")
+    } else if file_type.is_socket() {
+        Some("套接字")
+    } else if file_type.is_block_device() {
+        Some("块设备")
+    } else if file_type.is_char_device() {
+        Some("字符设备")
+    } else {
+        None
+    }
+}
+
+/**
+ * 创建按条目计数的进度条（处理文件、校验种子和校验文件时共用的样式）
+ */
+pub fn progress_bar(len: u64) -> ProgressBar {
+    let bar = ProgressBar::new(len);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {pos}/{len}\n{msg}",
+            )
+            .unwrap()
+            .progress_chars("█▓▒░ "),
+    );
+    bar
+}
 //EOP
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// 繁体、简体成对排列，可以双向转换
+const PAIRS: &str = "\
+    萬万與与醜丑專专業业叢丛東东絲丝丟丢兩两嚴严喪丧個个豐丰臨临為为麗丽舉举麼么義义烏乌樂乐喬乔習习鄉乡書书買买亂乱爭争虧亏雲云亞亚\
+    產产畝亩親亲褻亵嚲亸億亿僅仅從从侖仑倉仓儀仪們们價价眾众優优夥伙會会傴伛傘伞偉伟傳传傷伤倀伥倫伦傖伧偽伪佇伫體体傭佣僉佥俠侠侶侣\
+    僥侥偵侦側侧僑侨儈侩儕侪儂侬俁俣儔俦儼俨倆俩儷俪儉俭債债傾倾傯偬僂偻僨偾償偿儻傥儐傧儲储儺傩兒儿兌兑兗兖黨党蘭兰關关興兴茲兹養养\
+    獸兽囅冁內内岡冈冊册寫写軍军農农塚冢馮冯衝冲決决況况凍冻淨净淒凄涼凉淩凌減减湊凑凜凛幾几鳳凤處处鳧凫憑凭凱凯擊击鑿凿芻刍劉刘則则\
+    剛刚創创刪删別别剗刬剄刭劊刽劌刿剴剀劑剂剮剐劍剑剝剥劇剧勸劝辦办務务勱劢動动勵励勁劲勞劳勢势勳勋勩勚勻匀匭匦匱匮區区醫医華华協协\
+    單单賣卖盧卢滷卤臥卧衛卫卻却巹卺廠厂廳厅歷历曆历厲厉壓压厭厌厙厍廁厕廂厢厴厣廈厦廚厨廄厩廝厮縣县參参靉叆靆叇雙双發发變变敘叙疊叠\
+    葉叶號号歎叹嘰叽籲吁嚇吓呂吕嗎吗唚吣噸吨聽听啟启吳吴嘸呒囈呓嘔呕嚦呖唄呗員员咼呙嗆呛嗚呜詠咏嚨咙嚀咛噝咝響响啞哑噠哒嘵哓嗶哔噦哕\
+    嘩哗噲哙嚌哜噥哝喲哟嘜唛嘮唠嗩唢喚唤嘖啧嗇啬囀啭齧啮嘯啸噴喷嘍喽嚳喾囁嗫噯嗳噓嘘嚶嘤囑嘱嚕噜囂嚣團团園园圍围圇囵國国圖图圓圆聖圣\
+    壙圹場场壞坏塊块堅坚壇坛壢坜壩坝塢坞墳坟墜坠壟垄壚垆壘垒墾垦堊垩墊垫埡垭塏垲壎埙鹼碱堿碱塹堑墮堕壺壶聲声殼壳壽寿備备復复夠够頭头\
+    誇夸夾夹奪夺奮奋獎奖奧奥妝妆婦妇媽妈嫵妩嫗妪媯妫姍姗婁娄婭娅嬈娆嬌娇孌娈娛娱媧娲嫻娴嫿婳嬰婴嬋婵嬸婶媼媪嬡嫒嬪嫔嬙嫱嬤嬷孫孙學学\
+    孿孪寧宁寶宝實实寵宠審审憲宪宮宫寬宽賓宾寢寝對对尋寻導导將将爾尔塵尘嘗尝堯尧尷尴屍尸盡尽層层屬属屢屡嶼屿歲岁豈岂嶇岖崗岗峴岘嵐岚\
+    島岛嶺岭嶽岳崠岽巋岿嶨峃嶧峄峽峡嶢峣嶠峤崢峥巒峦嶗崂崍崃嶄崭嶸嵘嶔嵚嶁嵝巔巅鞏巩巰巯幣币帥帅師师幃帏帳帐簾帘幟帜帶带幀帧幫帮幬帱\
+    幘帻幗帼冪幂莊庄慶庆廬庐廡庑庫库應应廟庙龐庞廢废廎庼廩廪開开異异棄弃張张彌弥弳弪彎弯彈弹強强歸归當当錄录匯汇彙汇彥彦徹彻徑径徠徕\
+    憶忆懺忏憂忧愾忾懷怀態态慫怂憮怃慪怄悵怅愴怆憐怜總总懟怼懌怿戀恋懇恳惡恶慟恸懨恹愷恺惻恻惱恼惲恽悅悦懸悬慳悭憫悯驚惊懼惧慘惨懲惩\
+    憊惫愜惬慚惭憚惮慣惯慍愠憤愤憒愦願愿懾慑懣懑懶懒懍懔戇戆戔戋戲戏戧戗戰战戩戬戶户紮扎撲扑執执擴扩捫扪掃扫揚扬擾扰撫抚拋抛摶抟摳抠\
+    掄抡搶抢護护報报擔担擬拟攏拢揀拣擁拥攔拦擰拧撥拨擇择掛挂摯挚攣挛撾挝撻挞挾挟撓挠擋挡撟挢掙挣擠挤揮挥撈捞損损撿捡換换搗捣據据擄掳\
+    摑掴擲掷撣掸摻掺摜掼攬揽撳揿攙搀擱搁摟搂攪搅攜携攝摄攄摅擺摆搖摇擯摈攤摊攖撄撐撑攆撵擷撷擼撸攛撺擻擞攢攒敵敌斂敛數数齋斋斕斓斬斩\
+    斷断時时曠旷暘旸曇昙晝昼顯显晉晋曬晒曉晓曄晔暈晕暉晖暫暂曖暧術术機机殺杀雜杂權权條条來来楊杨榪杩傑杰極极構构樅枞樞枢棗枣櫪枥梘枧\
+    棖枨槍枪楓枫梟枭檸柠檉柽梔栀柵栅標标棧栈櫛栉櫳栊棟栋櫨栌櫟栎欄栏樹树棲栖樣样欒栾椏桠橈桡楨桢檔档榿桤橋桥樺桦檜桧槳桨樁桩夢梦檮梼\
+    棶梾檢检欞棂槨椁櫝椟槧椠樓楼欖榄櫬榇櫚榈櫸榉檟槚檻槛檳槟櫧槠橫横檣樯櫻樱櫫橥櫥橱櫓橹櫞橼簷檐檁檩歡欢歟欤歐欧殲歼歿殁殤殇殘残殞殒\
+    殮殓殫殚殯殡毆殴毀毁轂毂畢毕斃毙氈毡毿毵氌氇氣气氫氢氬氩氳氲漢汉湯汤洶汹溝沟沒没灃沣漚沤瀝沥淪沦滄沧溈沩滬沪濘泞淚泪澩泶瀧泷瀘泸\
+    濼泺瀉泻潑泼澤泽涇泾潔洁灑洒窪洼浹浃淺浅漿浆澆浇湞浈濁浊測测澮浍濟济瀏浏滻浐渾浑滸浒濃浓潯浔濤涛澇涝淶涞漣涟潿涠渦涡溳涢渙涣滌涤\
+    潤润澗涧漲涨澀涩淵渊漬渍瀆渎漸渐澠渑漁渔滲渗溫温遊游灣湾濕湿潰溃濺溅漵溆滎荥潷滗滾滚滯滞灩滟灄滠滿满瀅滢濾滤濫滥灤滦濱滨灘滩澦滪\
+    瀠潆瀟潇瀲潋濰潍潛潜瀦潴瀾澜瀨濑瀕濒灝灏滅灭燈灯靈灵災灾燦灿煬炀爐炉燉炖煒炜熗炝點点煉炼熾炽爍烁爛烂烴烃燭烛煙烟煩烦燒烧燁烨燴烩\
+    燙烫燼烬熱热煥焕燜焖燾焘愛爱爺爷牘牍牽牵犧牺犢犊狀状獷犷獁犸猶犹狽狈獮狝獰狞獨独狹狭獅狮獪狯猙狰獄狱猻狲獫猃獵猎獼猕玀猡豬猪貓猫\
+    蝟猬獻献獺獭璣玑瑒玚瑪玛瑋玮環环現现璽玺琺珐瓏珑璫珰琿珲璉琏瑣琐瓊琼瑤瑶璦瑷瓔璎瓚瓒甌瓯電电畫画暢畅疇畴癤疖療疗瘧疟癘疠瘍疡瘋疯\
+    皰疱癰痈痙痉癢痒瘂痖癆痨瘓痪癇痫癉瘅瘞瘗瘺瘘癱瘫癮瘾癭瘿癩癞癬癣癲癫皚皑皺皱盜盗盞盏監监盤盘蕩荡眥眦睜睁睞睐瞼睑瞞瞒矚瞩矯矫磯矶\
+    礬矾礦矿碭砀碼码磚砖硨砗硯砚碸砜礪砺礱砻礫砾礎础硜硁碩硕硤硖磽硗磑硙確确礙碍磧碛磣碜禮礼禕祎禰祢禎祯禱祷禍祸稟禀祿禄禪禅離离禿秃\
+    稈秆種种積积稱称穢秽穌稣穩稳穡穑窮穷竊窃竅窍窯窑竄窜窩窝窺窥竇窦豎竖競竞筆笔筍笋筧笕箋笺籠笼籩笾築筑篳筚篩筛簹筜箏筝籌筹簽签簡简\
+    籙箓籜箨籃篮籬篱籪簖籟籁糴籴類类秈籼糶粜糲粝粵粤糞粪糧粮糝糁餱糇緊紧縶絷糾纠紆纡紅红紂纣纖纤紇纥約约級级紈纨纊纩紀纪紉纫緯纬紜纭\
+    純纯紕纰紗纱綱纲納纳紝纴縱纵綸纶紛纷紙纸紋纹紡纺紖纼紐纽紓纾線线紺绀紲绁紱绂練练組组紳绅細细織织終终縐绉絆绊紼绋絀绌紹绍繹绎經经\
+    紿绐綁绑絨绒結结絝绔繞绕絰绖絎绗繪绘給给絢绚絳绛絡络絕绝絞绞統统綆绠綃绡絹绢繡绣綌绤綏绥絛绦繼继綈绨績绩緒绪綾绫續续綺绮緋绯綽绰\
+    緔绱緄绲繩绳維维綿绵綬绶繃绷綢绸綯绹綹绺綣绻綜综綻绽綰绾綠绿綴缀緇缁緙缂緗缃緘缄緬缅纜缆緹缇緲缈緝缉縕缊繢缋緦缌綞缍緞缎緶缏緱缑\
+    縋缒緩缓締缔縷缕編编緡缗緣缘縉缙縛缚縟缛縝缜縫缝縗缞縞缟纏缠縭缡縊缢縑缣繽缤縹缥縵缦縲缧纓缨縮缩繆缪繅缫纈缬繚缭繕缮繒缯韁缰繯缳\
+    繳缴纘缵罌罂網网羅罗罰罚罷罢羆罴羈羁羋芈羥羟翹翘耬耧聳耸恥耻聶聂聾聋職职聹聍聯联聵聩聰聪肅肃腸肠膚肤骯肮腎肾腫肿脹胀脅胁膽胆勝胜\
+    朧胧腖胨臚胪脛胫膠胶脈脉膾脍髒脏臍脐腦脑膿脓臠脔腳脚脫脱腡脶臉脸臘腊膕腘齶腭膩腻靦腼膃腽騰腾臏膑臢臜輿舆艤舣艦舰艙舱艫舻艱艰豔艳\
+    藝艺節节薌芗蕪芜蘆芦蓯苁葦苇藶苈莧苋萇苌蒼苍苧苎蘇苏檾苘蘋苹莖茎蘢茏蔦茑塋茔煢茕荊荆薦荐莢荚蕘荛蓽荜蕎荞薈荟薺荠榮荣葷荤犖荦熒荧\
+    蕁荨藎荩蓀荪蔭荫蕒荬葒荭藥药蒞莅萊莱蓮莲蒔莳萵莴薟莶獲获蕕莸瑩莹鶯莺蓴莼蘀萚蘿萝螢萤營营縈萦蕭萧薩萨蔥葱蕆蒇蕢蒉蔣蒋蔞蒌藍蓝薊蓟\
+    蘺蓠蕷蓣鎣蓥驀蓦薔蔷蘞蔹藺蔺藹蔼蘄蕲蘊蕴藪薮蘚藓蘗蘖虜虏慮虑虛虚蟲虫虯虬蟣虮雖虽蝦虾蠆虿蝕蚀蟻蚁螞蚂蠶蚕蠔蚝蜆蚬蠱蛊蠣蛎蟶蛏蠻蛮\
+    蟄蛰蛺蛱蟯蛲螄蛳蠐蛴蛻蜕蝸蜗蠟蜡蠅蝇蟈蝈蟬蝉蠍蝎螻蝼蠑蝾螿螀補补襯衬袞衮襖袄褘袆襪袜襲袭襏袯裝装襠裆褌裈褳裢襝裣褲裤襇裥褸褛襤褴\
+    見见觀观規规覓觅視视覘觇覽览覺觉覬觊覡觋覿觌覥觍覦觎覯觏覲觐覷觑觴觞觸触觶觯讋詟譽誉謄誊計计訂订訃讣認认譏讥訐讦訌讧討讨讓让訕讪\
+    訖讫託托訓训議议訊讯記记訒讱講讲諱讳謳讴詎讵訝讶訥讷許许訛讹論论訩讻訟讼諷讽設设訪访訣诀證证詁诂訶诃評评詛诅識识詗诇詐诈訴诉診诊\
+    詆诋謅诌詞词詘诎詔诏詖诐譯译詒诒誆诓誄诔試试詿诖詩诗詰诘詼诙誠诚誅诛詵诜話话誕诞詬诟詮诠詭诡詢询詣诣諍诤該该詳详詫诧諢诨詡诩譸诪\
+    誡诫誣诬語语誚诮誤误誥诰誘诱誨诲誑诳說说誦诵誒诶請请諸诸諏诹諾诺讀读諑诼誹诽課课諉诿諛谀誰谁諗谂調调諂谄諒谅諄谆誶谇談谈誼谊謀谋\
+    諶谌諜谍謊谎諫谏諧谐謔谑謁谒謂谓諤谔諭谕諼谖讒谗諮谘諳谙諺谚諦谛謎谜諞谝謨谟讜谠謝谢謠谣謗谤謚谥謙谦謐谧謹谨謾谩謫谪譾谫謬谬譚谭\
+    譖谮譙谯讕谰譜谱譎谲讞谳譴谴譫谵讖谶豶豮貝贝貞贞負负貟贠貢贡財财責责賢贤敗败賬账貨货質质販贩貪贪貧贫貶贬購购貯贮貫贯貳贰賤贱賁贲\
+    貰贳貼贴貴贵貺贶貸贷貿贸費费賀贺貽贻賊贼贄贽賈贾賄贿貲赀賃赁賂赂贓赃資资賅赅贐赆賕赇賑赈賚赉賒赊賦赋賭赌齎赍贖赎賞赏賜赐贔赑賙赒\
+    賡赓賠赔賧赕賴赖賵赗贅赘賻赙賺赚賽赛賾赜贗赝贊赞贇赟贈赠贍赡贏赢贛赣赬赪趙赵趕赶趨趋趲趱躉趸躍跃蹌跄蹠跖躒跞踐践躂跶蹺跷蹕跸躚跹\
+    躋跻踴踊躊踌蹤踪躓踬躑踯躡蹑蹣蹒躕蹰躥蹿躪躏躦躜軀躯車车軋轧軌轨軒轩軑轪軔轫轉转軛轭輪轮軟软轟轰軲轱軻轲轤轳軸轴軹轵軼轶軤轷軫轸\
+    轢轹軺轺輕轻軾轼載载輊轾轎轿輈辀輇辁輅辂較较輒辄輔辅輛辆輦辇輩辈輝辉輥辊輞辋輬辌輟辍輜辎輳辏輻辐輯辑轀辒輸输轡辔轅辕輾辗轄辖轆辘\
+    轍辙轔辚辭辞辯辩邊边遼辽達达遷迁過过邁迈運运還还這这進进遠远違违連连遲迟邇迩逕迳適适選选遜逊遞递邐逦邏逻遺遗遙遥鄧邓鄺邝鄔邬郵邮\
+    鄒邹鄴邺鄰邻郟郏鄶郐鄭郑鄆郓酈郦鄖郧鄲郸醞酝醬酱釀酿釁衅釋释鑒鉴鑾銮鏨錾釓钆釔钇針针釘钉釗钊釙钋釕钌釷钍釺钎釧钏釤钐釣钓釩钒釹钕\
+    釵钗鈣钙鈦钛鉅钜鈍钝鈔钞鈉钠鋇钡鋼钢鈑钣鈐钤鈴铃鈮铌鈹铍鈷钴鉀钾鈿钿鈾铀鐵铁鉑铂鑠铄鉛铅鉚铆鉍铋鈰铈鉉铉鉈铊鉗钳鉤钩銀银銅铜銘铭\
+    鋁铝銷销鋤锄鋪铺鋒锋鋅锌鋸锯錢钱錦锦錨锚錯错鍵键鍋锅鍍镀鐘钟鍾钟鎖锁鎮镇鏈链鏡镜鐺铛鑄铸鑰钥鑲镶長长門门閂闩閃闪閆闫閉闭問问闖闯\
+    閏闰閒闲間间閔闵悶闷閘闸鬧闹閨闺聞闻閩闽閭闾閥阀閣阁閡阂閫阃閬阆閱阅閻阎閹阉閾阈闊阔闋阕闌阑闈闱闔阖闐阗闡阐闢辟隊队陽阳陰阴陣阵\
+    階阶際际陸陆隴陇陳陈陘陉陝陕隉陧隕陨險险隨随隱隐隸隶難难雛雏雞鸡霧雾霽霁靂雳靄霭靜静靨靥韃鞑韉鞯韋韦韌韧韓韩韙韪韜韬韻韵頁页頂顶\
+    頃顷順顺項项須须頊顼頑顽顧顾頓顿頎颀頒颁頌颂頏颃預预顱颅領领頗颇頸颈頡颉頰颊頜颌潁颍頦颏頹颓頷颔穎颖顆颗題题顎颚顏颜額额顓颛顛颠\
+    顢颟顥颢顫颤顬颥顰颦顴颧風风颺飏颭飐颮飑颯飒颶飓颸飔颼飕飄飘飆飙飛飞饗飨饜餍飣饤飢饥飩饨飪饪飫饫飭饬飯饭飲饮餞饯飾饰飽饱飼饲飿饳\
+    飴饴餌饵饒饶餉饷餃饺餅饼餑饽餓饿餒馁餚肴館馆餛馄餡馅餿馊饞馋饅馒饉馑饈馐饃馍饋馈饑饥馬马馭驭馱驮馳驰馴驯駁驳駐驻駑驽駒驹駕驾駛驶\
+    駙驸駝驼駟驷駘骀驛驿驗验騎骑騙骗騷骚驅驱驕骄驢驴驥骥驟骤驤骧驊骅驍骁驂骖驃骠驪骊髏髅髖髋髕髌鬢鬓魎魉魘魇魚鱼魯鲁鮑鲍鮮鲜鯉鲤鯨鲸\
+    鰻鳗鱉鳖鱷鳄鳥鸟鳩鸠鳴鸣鴉鸦鴨鸭鴛鸳鴦鸯鴻鸿鵝鹅鵬鹏鶴鹤鷹鹰鸚鹦鸞鸾鹵卤麥麦麩麸黃黄黌黉黷黩鼴鼹齊齐齒齿齡龄齦龈齪龊龍龙龔龚龕龛\
+    龜龟髮发鍊炼劃划樸朴";
+/// 只用于繁体转简体的字（简体字本身也是常用的繁体字，如 "後" 和 "后"）
+const TO_HANS_ONLY: &str = "\
+    隻只乾干幹干後后裏里餘余於于臺台颱台檯台鬥斗穀谷麵面鬆松準准齣出韆千闆板鉋刨衚胡瀋沈埰采鬱郁醃腌衊蔑嫋袅鹹咸徵征製制誌志範范係系\
+    繫系錶表迴回佈布週周捨舍嚮向姦奸纔才籤签";
+
+static TO_HANS: LazyLock<HashMap<char, char>> =
+    LazyLock::new(|| pairs(PAIRS).chain(pairs(TO_HANS_ONLY)).collect());
+
+/// 简体对应多个繁体时，取表中最先出现的一个
+static TO_HANT: LazyLock<HashMap<char, char>> = LazyLock::new(|| {
+    let mut map = HashMap::new();
+    for (hant, hans) in pairs(PAIRS) {
+        map.entry(hans).or_insert(hant);
+    }
+    map
+});
+
+/// 转换的目标字形
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Script {
+    /// 简体
+    Hans,
+    /// 繁体
+    Hant,
+}
+
+impl Script {
+    /**
+     * 解析目标字形名称：hans / zh-hans / simplified，hant / zh-hant / traditional
+     */
+    pub fn parse(name: &str) -> Option<Script> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "hans" | "zh-hans" | "zh-cn" | "simplified" => Some(Script::Hans),
+            "hant" | "zh-hant" | "zh-tw" | "traditional" => Some(Script::Hant),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * 简繁转换（逐字对照，离线）
+ *
+ * 只做单字转换，不处理词语；简体对应多个繁体的字（如 "发" 对应 "發" 和 "髮"）取最常用的一个，
+ * 因此转为繁体只是近似结果。转换前后的字符数相同，可以按字符位置对应原文。
+ */
+pub fn convert(text: &str, script: Script) -> String {
+    let table = match script {
+        Script::Hans => &*TO_HANS,
+        Script::Hant => &*TO_HANT,
+    };
+    text.chars().map(|c| *table.get(&c).unwrap_or(&c)).collect()
+}
+
+pub fn to_hans(text: &str) -> String {
+    convert(text, Script::Hans)
+}
+
+fn pairs(table: &'static str) -> impl Iterator<Item = (char, char)> {
+    let mut chars = table.chars();
+    std::iter::from_fn(move || Some((chars.next()?, chars.next()?)))
+}
+//EOP